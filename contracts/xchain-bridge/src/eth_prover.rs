@@ -0,0 +1,193 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near, require, AccountId, Gas, NearToken, Promise, PromiseOrValue};
+use xchain_core::eth::{self, EthLog};
+use xchain_core::events::NepXChainEvent;
+use xchain_core::{promise_value, AssetStandard, BridgePayload, BridgeProof, CanonicalAssetId, ChainId};
+
+use crate::{TransferPath, XChainBridge, XChainBridgeExt};
+
+const GAS_BLOCK_HASH: Gas = Gas::from_tgas(10);
+const GAS_LAST_BLOCK_NUMBER: Gas = Gas::from_tgas(5);
+const GAS_FINISH_ETH_BRIDGE_IN: Gas = Gas::from_tgas(200);
+
+pub const LOCKED_EVENT_SIGNATURE: &str = "Locked(address,address,uint256,string,uint256)";
+
+/// Where `bridge_in` looks up Ethereum block hashes and which locker contract's
+/// `Locked` events it accepts.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct EthProverConfig {
+    pub light_client: AccountId,
+    pub locker_address: String,
+    pub source_chain: ChainId,
+}
+
+struct LockedEvent {
+    token: [u8; 20],
+    amount: u128,
+    recipient: String,
+    nonce: u128,
+}
+
+#[near]
+impl XChainBridge {
+    pub fn set_eth_prover(&mut self, config: Option<EthProverConfig>) {
+        self.assert_owner();
+        if let Some(config) = &config {
+            require!(parse_address(&config.locker_address).is_some(), "Invalid locker address");
+        }
+        let old_light_client = self.eth_prover.as_ref().map(|c| c.light_client.clone());
+        let new_light_client = config.as_ref().map(|c| c.light_client.clone());
+        self.eth_prover = config;
+        NepXChainEvent::EthProverChanged {
+            caller: env::predecessor_account_id(),
+            old_light_client,
+            new_light_client,
+        }.emit();
+    }
+
+    pub fn get_eth_prover(&self) -> Option<EthProverConfig> {
+        self.eth_prover.clone()
+    }
+
+    /// Mints once the light client confirms the header the proof was checked
+    /// against and its head is at least the chain's `finality_blocks` past it.
+    /// Otherwise, or if the transfer was processed meanwhile, the deposit is
+    /// refunded to `caller`.
+    #[private]
+    #[payable]
+    pub fn finish_eth_bridge_in(
+        &mut self,
+        payload: BridgePayload,
+        block_height: u64,
+        header_hash: [u8; 32],
+        caller: AccountId,
+    ) -> PromiseOrValue<bool> {
+        let block_hash = promise_value(0)
+            .and_then(|result| near_sdk::borsh::from_slice::<Option<[u8; 32]>>(&result).ok())
+            .flatten();
+        let last_block_number = promise_value(1).and_then(|result| near_sdk::borsh::from_slice::<u64>(&result).ok());
+        let final_enough = match self.supported_chains.get(&payload.source_chain) {
+            Some(config) if config.enabled => last_block_number
+                .is_some_and(|head| head >= block_height.saturating_add(config.finality_blocks)),
+            _ => false,
+        };
+        if block_hash != Some(header_hash) || !final_enough || !self.can_process(&payload.source_chain, payload.nonce.0, TransferPath::EthProof) {
+            let deposit = env::attached_deposit();
+            if !deposit.is_zero() {
+                let _ = Promise::new(caller).transfer(deposit);
+            }
+            return PromiseOrValue::Value(false);
+        }
+        PromiseOrValue::Promise(self.process_payload(payload, None, TransferPath::EthProof, caller))
+    }
+}
+
+impl XChainBridge {
+    /// Checks the receipt and transaction proofs against their own header and
+    /// decodes the transfer, with `source_tx_hash` taken from the proven
+    /// transaction, before asking the light client whether that header is
+    /// final, so a bad proof fails while the deposit is still with the caller.
+    pub(crate) fn request_eth_block_hash(&self, proof: BridgeProof, receiver_id: AccountId) -> Promise {
+        let config = self.eth_prover.clone().expect("Ethereum prover not configured");
+        let eth_proof = proof.eth_proof.as_ref().expect("Missing Ethereum proof");
+        let header_hash = env::keccak256_array(&eth_proof.header_rlp);
+        let (log, tx_hash) = eth::prove_log(eth_proof, proof.block_height, &header_hash);
+        let event = decode_locked_event(&log, &config);
+        let source_tx_hash = format!("0x{}", hex::encode(tx_hash));
+        require!(
+            proof.source_tx_hash.is_empty() || proof.source_tx_hash.eq_ignore_ascii_case(&source_tx_hash),
+            "Source tx hash mismatch"
+        );
+
+        let receiver: AccountId = event.recipient.parse().expect("Invalid recipient account");
+        require!(receiver == receiver_id, "Receiver mismatch");
+
+        let payload = BridgePayload {
+            nonce: U128(event.nonce),
+            source_chain: config.source_chain.clone(),
+            target_chain: self.chain_id.clone(),
+            asset: CanonicalAssetId {
+                source_chain_id: config.source_chain,
+                source_contract: format!("0x{}", hex::encode(event.token)),
+                asset_standard: AssetStandard::ERC20,
+            },
+            amount: U128(event.amount),
+            receiver,
+            source_tx_hash,
+        };
+        self.assert_chain_enabled(&payload.source_chain);
+        require!(
+            !self.is_transfer_processed(payload.source_chain.clone(), payload.nonce, Some(TransferPath::EthProof)),
+            "Transfer already processed"
+        );
+        require!(
+            self.can_process(&payload.source_chain, payload.nonce.0, TransferPath::EthProof),
+            "Unexpected nonce"
+        );
+
+        Promise::new(config.light_client.clone())
+            .function_call(
+                "block_hash_safe".to_string(),
+                near_sdk::borsh::to_vec(&proof.block_height).expect("Failed to serialize block height"),
+                NearToken::from_yoctonear(0),
+                GAS_BLOCK_HASH,
+            )
+            .and(Promise::new(config.light_client).function_call(
+                "last_block_number".to_string(),
+                vec![],
+                NearToken::from_yoctonear(0),
+                GAS_LAST_BLOCK_NUMBER,
+            ))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FINISH_ETH_BRIDGE_IN)
+                    .with_attached_deposit(env::attached_deposit())
+                    .finish_eth_bridge_in(payload, proof.block_height, header_hash, env::predecessor_account_id())
+            )
+    }
+}
+
+fn decode_locked_event(log: &EthLog, config: &EthProverConfig) -> LockedEvent {
+    let locker = parse_address(&config.locker_address).expect("Invalid locker address");
+    require!(log.address == locker, "Log not emitted by locker");
+    require!(
+        log.topics.len() == 3 && log.topics[0] == env::keccak256_array(LOCKED_EVENT_SIGNATURE.as_bytes()),
+        "Log is not a Locked event"
+    );
+
+    let mut token = [0u8; 20];
+    token.copy_from_slice(&log.topics[1][12..]);
+
+    let amount = abi_u128(&log.data, 0);
+    let recipient_offset = abi_usize(&log.data, 32);
+    let nonce = abi_u128(&log.data, 64);
+    let recipient_len = abi_usize(&log.data, recipient_offset);
+    let recipient_start = recipient_offset.checked_add(32).expect("Invalid Locked event data");
+    let recipient = log
+        .data
+        .get(recipient_start..recipient_start.saturating_add(recipient_len))
+        .expect("Invalid Locked event data");
+    let recipient = String::from_utf8(recipient.to_vec()).expect("Invalid Locked event data");
+
+    LockedEvent { token, amount, recipient, nonce }
+}
+
+fn abi_word(data: &[u8], offset: usize) -> &[u8] {
+    data.get(offset..offset.saturating_add(32)).filter(|w| w.len() == 32).expect("Invalid Locked event data")
+}
+
+fn abi_u128(data: &[u8], offset: usize) -> u128 {
+    let word = abi_word(data, offset);
+    require!(word[..16].iter().all(|b| *b == 0), "Value does not fit in u128");
+    u128::from_be_bytes(word[16..].try_into().unwrap())
+}
+
+fn abi_usize(data: &[u8], offset: usize) -> usize {
+    usize::try_from(abi_u128(data, offset)).expect("Invalid Locked event data")
+}
+
+fn parse_address(address: &str) -> Option<[u8; 20]> {
+    let bytes = hex::decode(address.strip_prefix("0x")?).ok()?;
+    bytes.try_into().ok()
+}
@@ -1,24 +1,87 @@
-use near_sdk::{near, env, require, AccountId, Gas, NearToken, Promise, PublicKey};
-use near_sdk::json_types::U128;
-use near_sdk::store::{IterableSet, IterableMap};
+use near_sdk::{near, env, require, AccountId, Gas, NearToken, Promise, PromiseOrValue, Timestamp};
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use near_sdk::store::{IterableMap, IterableSet, LookupMap, Vector};
 use xchain_core::{
-    BridgeProof, BridgeRoute, CanonicalAssetId, SecurityModel, XChainCore, BridgeDirection, ReceiptTokenMetadata, ChainId
+    AuthorizedRoute, BridgePayload, BridgeProof, BridgeRoute, CanonicalAssetId, CommitteeProof, SecurityModel, XChainCore, BridgeDirection, ReceiptTokenMetadata, ChainConfig, ChainId, TokenCallPayload, ValidatorKey, promise_succeeded
 };
+use xchain_core::committee::{Committee, ValidatorConfig, ValidatorEpoch};
+use xchain_core::events::NepXChainEvent;
+use xchain_core::nonces::{NonceStorageUsage, NonceTracker};
+
+mod eth_prover;
+mod migration;
+mod registry;
+mod routes;
+mod staking;
+mod token_call;
+
+pub use eth_prover::EthProverConfig;
+pub use routes::{AssetRecord, RouteChange};
+pub use staking::{SignedPayload, ValidatorBond};
 
 const GAS_MINT_TOKEN: Gas = Gas::from_tgas(10);
 const GAS_INIT_TOKEN: Gas = Gas::from_tgas(10);
+const GAS_ON_MINT: Gas = Gas::from_tgas(10);
+const GAS_ON_BURN: Gas = Gas::from_tgas(10);
+const GAS_ON_TOKEN_DEPLOYED: Gas = Gas::from_tgas(170);
 const BRIDGE_FEE: NearToken = NearToken::from_millinear(100);
+const DEFAULT_PAGE_LIMIT: u32 = 100;
 
 #[near(contract_state)]
 pub struct XChainBridge {
     pub receipt_token_code: Vec<u8>,
     pub canonical_to_receipt: IterableMap<String, AccountId>,
     pub receipt_to_canonical: IterableMap<AccountId, CanonicalAssetId>,
-    pub processed_proofs: IterableSet<Vec<u8>>,
+    pub legacy_proofs: IterableSet<Vec<u8>>,
+    pub next_nonces: LookupMap<(ChainId, TransferPath), u128>,
     pub owner_id: AccountId,
-    pub validators: IterableSet<PublicKey>,
+    pub committee: Committee,
     pub paused: bool,
     pub treasury: AccountId,
+    pub pending_claims: LookupMap<AccountId, Vec<PendingClaim>>,
+    pub claim_delegates: LookupMap<AccountId, AccountId>,
+    pub bonds: LookupMap<ValidatorKey, ValidatorBond>,
+    pub unbonding_period: u64,
+    pub min_validator_bond: U128,
+    pub eth_prover: Option<EthProverConfig>,
+    pub replay: NonceTracker,
+    pub eth_replay: NonceTracker,
+    pub chain_id: ChainId,
+    pub supported_chains: IterableMap<ChainId, ChainConfig>,
+    pub deployed_at: Timestamp,
+    pub route_history: Vector<RouteChange>,
+    pub asset_records: LookupMap<AccountId, AssetRecord>,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct PendingClaim {
+    pub canonical_asset: CanonicalAssetId,
+    pub receipt_token: AccountId,
+    pub amount: U128,
+}
+
+/// How a transfer was proven. Each path has its own nonce space per source
+/// chain, so committee-attested and light-client-proven transfers cannot
+/// block each other.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TransferPath {
+    #[default]
+    Committee,
+    EthProof,
+}
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct BridgedAsset {
+    pub receipt_token: AccountId,
+    pub canonical_asset: CanonicalAssetId,
+    pub caip19: String,
+    /// `None` for tokens deployed before the upgrade until
+    /// `refresh_bridge_route` has synced them.
+    pub bridge_route: Option<BridgeRoute>,
+    pub total_locked: Option<U128>,
 }
 
 impl Default for XChainBridge {
@@ -30,31 +93,532 @@ impl Default for XChainBridge {
 #[near]
 impl XChainBridge {
     #[init]
-    pub fn new(owner_id: AccountId, initial_validators: Vec<PublicKey>) -> Self {
-        let mut v = IterableSet::new(b"v");
-        for key in initial_validators {
-            v.insert(key);
-        }
-        Self {
+    pub fn new(owner_id: AccountId, initial_validators: Vec<ValidatorKey>, chain_id: ChainId) -> Self {
+        let mut bridge = Self {
             receipt_token_code: vec![],
             canonical_to_receipt: IterableMap::new(b"c"),
             receipt_to_canonical: IterableMap::new(b"r"),
-            processed_proofs: IterableSet::new(b"p"),
+            legacy_proofs: IterableSet::new(b"p"),
+            next_nonces: LookupMap::new(b"o"),
             owner_id: owner_id.clone(),
-            validators: v,
+            committee: Committee::new(b"v", initial_validators),
             paused: false,
             treasury: owner_id,
-        }
+            pending_claims: LookupMap::new(b"e"),
+            claim_delegates: LookupMap::new(b"g"),
+            bonds: LookupMap::new(b"b"),
+            unbonding_period: staking::DEFAULT_UNBONDING_PERIOD,
+            min_validator_bond: U128(staking::DEFAULT_MIN_VALIDATOR_BOND),
+            eth_prover: None,
+            replay: NonceTracker::new(b"n"),
+            eth_replay: NonceTracker::new(b"q"),
+            chain_id,
+            supported_chains: IterableMap::new(b"s"),
+            deployed_at: env::block_timestamp(),
+            route_history: Vector::new(b"h"),
+            asset_records: LookupMap::new(b"a"),
+        };
+        bridge.record_route_change();
+        bridge
     }
 
     pub fn set_receipt_token_code(&mut self, code: Vec<u8>) {
         self.assert_owner();
-        self.receipt_token_code = code;
+        let old_code = std::mem::replace(&mut self.receipt_token_code, code);
+        NepXChainEvent::ReceiptTokenCodeChanged {
+            caller: env::predecessor_account_id(),
+            old_code_hash: Self::code_hash(&old_code),
+            new_code_hash: Self::code_hash(&self.receipt_token_code),
+        }.emit();
     }
     
     pub fn set_treasury(&mut self, treasury: AccountId) {
         self.assert_owner();
-        self.treasury = treasury;
+        let old_treasury = std::mem::replace(&mut self.treasury, treasury.clone());
+        NepXChainEvent::TreasuryChanged {
+            caller: env::predecessor_account_id(),
+            old_treasury,
+            new_treasury: treasury,
+        }.emit();
+    }
+
+    pub fn get_receipt_token_code_hash(&self) -> Option<Base58CryptoHash> {
+        Self::code_hash(&self.receipt_token_code)
+    }
+
+    pub fn set_claim_delegate(&mut self, delegate_id: Option<AccountId>) {
+        let account_id = env::predecessor_account_id();
+        match delegate_id {
+            Some(delegate_id) => {
+                self.claim_delegates.insert(account_id, delegate_id);
+            }
+            None => {
+                self.claim_delegates.remove(&account_id);
+            }
+        }
+    }
+
+    #[payable]
+    pub fn claim(&mut self, canonical_asset: CanonicalAssetId, receiver_id: Option<AccountId>) -> Promise {
+        self.assert_not_paused();
+
+        let caller = env::predecessor_account_id();
+        let receiver_id = receiver_id.unwrap_or_else(|| caller.clone());
+        require!(
+            caller == receiver_id || self.claim_delegates.get(&receiver_id) == Some(&caller),
+            "Not authorized to claim for receiver"
+        );
+
+        let claims = self.pending_claims.get_mut(&receiver_id).expect("Nothing to claim");
+        let index = claims
+            .iter()
+            .position(|c| c.canonical_asset.key() == canonical_asset.key())
+            .expect("Nothing to claim");
+        let claim = claims.swap_remove(index);
+        if claims.is_empty() {
+            self.pending_claims.remove(&receiver_id);
+        }
+
+        if self.receipt_token_for(&claim.canonical_asset).is_some() {
+            return Self::mint_receipt(&claim.receipt_token, &receiver_id, claim.amount)
+                .then(Self::escrow_on_failure(claim.receipt_token, receiver_id, claim.amount, claim.canonical_asset));
+        }
+
+        // The token failed to deploy when the claim was escrowed.
+        let (receipt_token, deploy) = self.deploy_receipt_token(&claim.canonical_asset);
+        self.record_locked(&receipt_token, claim.amount);
+        deploy.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_ON_TOKEN_DEPLOYED)
+                .on_token_deployed(
+                    receipt_token,
+                    receiver_id,
+                    claim.amount,
+                    claim.canonical_asset,
+                    None,
+                    caller,
+                    env::attached_deposit(),
+                ),
+        )
+    }
+
+    pub fn rotate_validators(&mut self, validators: Vec<ValidatorConfig>, quorum: u64, proof: CommitteeProof) -> u64 {
+        for validator in &validators {
+            self.assert_bonded(&validator.public_key);
+        }
+        let epoch = self.committee.rotate(validators, quorum, &proof);
+        self.record_route_change();
+        epoch
+    }
+
+    pub fn set_quorum(&mut self, quorum: u64) {
+        self.assert_owner();
+        let old_quorum = self.committee.set_quorum(quorum);
+        self.record_route_change();
+        NepXChainEvent::QuorumChanged {
+            caller: env::predecessor_account_id(),
+            epoch: self.committee.current_epoch(),
+            old_quorum,
+            new_quorum: quorum,
+        }.emit();
+    }
+
+    pub fn set_epoch_grace_period(&mut self, grace_period: u64) {
+        self.assert_owner();
+        let old_grace_period = self.committee.grace_period();
+        self.committee.set_grace_period(grace_period);
+        NepXChainEvent::EpochGracePeriodChanged {
+            caller: env::predecessor_account_id(),
+            old_grace_period,
+            new_grace_period: grace_period,
+        }.emit();
+    }
+
+    pub fn set_max_validators(&mut self, max_validators: u32) {
+        self.assert_owner();
+        let old_max_validators = self.committee.set_max_validators(max_validators);
+        NepXChainEvent::MaxValidatorsChanged {
+            caller: env::predecessor_account_id(),
+            old_max_validators,
+            new_max_validators: max_validators,
+        }.emit();
+    }
+
+    pub fn get_max_validators(&self) -> u32 {
+        self.committee.max_validators()
+    }
+
+    pub fn get_current_epoch(&self) -> u64 {
+        self.committee.current_epoch()
+    }
+
+    pub fn get_validator_epoch(&self, epoch: Option<u64>) -> Option<ValidatorEpoch> {
+        let epoch = epoch.unwrap_or_else(|| self.committee.current_epoch());
+        self.committee.get_epoch(epoch).cloned()
+    }
+
+    /// Registers or updates a remote chain the bridge accepts transfers from and
+    /// to; `None` removes it.
+    pub fn set_supported_chain(&mut self, chain_id: ChainId, config: Option<ChainConfig>) {
+        self.assert_owner();
+        require!(chain_id != self.chain_id, "Cannot register own chain");
+        match &config {
+            Some(config) => {
+                self.supported_chains.insert(chain_id.clone(), config.clone());
+            }
+            None => {
+                self.supported_chains.remove(&chain_id);
+            }
+        }
+        NepXChainEvent::SupportedChainChanged {
+            caller: env::predecessor_account_id(),
+            chain_id,
+            config,
+        }.emit();
+    }
+
+    pub fn get_chain_id(&self) -> ChainId {
+        self.chain_id.clone()
+    }
+
+    pub fn get_supported_chain(&self, chain_id: ChainId) -> Option<ChainConfig> {
+        self.supported_chains.get(&chain_id).cloned()
+    }
+
+    pub fn get_supported_chains(&self) -> Vec<(ChainId, ChainConfig)> {
+        self.supported_chains
+            .iter()
+            .map(|(chain_id, config)| (chain_id.clone(), config.clone()))
+            .collect()
+    }
+
+    /// Receipt tokens with the route this bridge last set on each and the
+    /// amount locked on the source chain through this bridge.
+    pub fn list_bridged_assets(&self, from_index: Option<u32>, limit: Option<u32>) -> Vec<BridgedAsset> {
+        self.receipt_to_canonical
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|(receipt_token, canonical_asset)| {
+                let record = self.asset_records.get(receipt_token);
+                BridgedAsset {
+                    receipt_token: receipt_token.clone(),
+                    canonical_asset: canonical_asset.clone(),
+                    caip19: canonical_asset.to_caip19(),
+                    bridge_route: record.map(|r| r.bridge_route.clone()),
+                    total_locked: record.map(|r| r.total_locked),
+                }
+            })
+            .collect()
+    }
+
+    pub fn bridged_asset_count(&self) -> u32 {
+        self.receipt_to_canonical.len()
+    }
+
+    pub fn get_receipt_token(&self, canonical_asset: CanonicalAssetId) -> Option<AccountId> {
+        self.receipt_token_for(&canonical_asset).cloned()
+    }
+
+    pub fn get_receipt_token_by_caip19(&self, asset: String) -> Option<AccountId> {
+        let asset: CanonicalAssetId = asset.parse().unwrap_or_else(|e: String| env::panic_str(&e));
+        self.get_receipt_token(asset)
+    }
+
+    /// With `next_nonce` set, transfers from `source_chain` proven through
+    /// `path` (default `Committee`) must arrive with consecutive nonces
+    /// starting at it; `None` accepts any unused nonce.
+    pub fn set_strict_ordering(&mut self, source_chain: ChainId, next_nonce: Option<U128>, path: Option<TransferPath>) {
+        self.assert_owner();
+        let key = (source_chain.clone(), path.unwrap_or_default());
+        match next_nonce {
+            Some(next_nonce) => {
+                self.next_nonces.insert(key, next_nonce.0);
+            }
+            None => {
+                self.next_nonces.remove(&key);
+            }
+        }
+        NepXChainEvent::StrictOrderingChanged {
+            caller: env::predecessor_account_id(),
+            source_chain,
+            next_nonce,
+        }.emit();
+    }
+
+    pub fn get_next_nonce(&self, source_chain: ChainId, path: Option<TransferPath>) -> Option<U128> {
+        self.next_nonces.get(&(source_chain, path.unwrap_or_default())).map(|nonce| U128(*nonce))
+    }
+
+    pub fn is_transfer_processed(&self, source_chain: ChainId, nonce: U128, path: Option<TransferPath>) -> bool {
+        self.replay(path.unwrap_or_default()).is_processed(&source_chain, nonce.0)
+    }
+
+    pub fn set_nonce_watermark(&mut self, source_chain: ChainId, watermark: U128, path: Option<TransferPath>) {
+        self.assert_owner();
+        let replay = self.replay_mut(path.unwrap_or_default());
+        let old_watermark = U128(replay.watermark(&source_chain));
+        replay.raise_watermark(&source_chain, watermark.0);
+        NepXChainEvent::NonceWatermarkRaised {
+            caller: env::predecessor_account_id(),
+            source_chain,
+            old_watermark,
+            new_watermark: watermark,
+        }.emit();
+    }
+
+    pub fn get_nonce_watermark(&self, source_chain: ChainId, path: Option<TransferPath>) -> U128 {
+        U128(self.replay(path.unwrap_or_default()).watermark(&source_chain))
+    }
+
+    pub fn get_replay_storage_usage(&self, path: Option<TransferPath>) -> NonceStorageUsage {
+        self.replay(path.unwrap_or_default()).storage_usage()
+    }
+
+    pub fn get_pending_claims(&self, account_id: AccountId) -> Vec<PendingClaim> {
+        self.pending_claims.get(&account_id).cloned().unwrap_or_default()
+    }
+
+    pub fn get_claim_delegate(&self, account_id: AccountId) -> Option<AccountId> {
+        self.claim_delegates.get(&account_id).cloned()
+    }
+
+    #[private]
+    pub fn on_mint(
+        &mut self,
+        receipt_token: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        canonical_asset: CanonicalAssetId,
+    ) -> bool {
+        if promise_succeeded(0) {
+            return true;
+        }
+        self.escrow_claim(receipt_token, receiver_id, amount, canonical_asset);
+        false
+    }
+
+    /// Mints once the receipt token is deployed. If the deployment failed the
+    /// asset mapping and record are rolled back so the next transfer deploys
+    /// again, the deposit goes back to `caller`, the account that paid it, and
+    /// the amount is held as a claim that `claim` delivers after redeploying
+    /// the token.
+    #[private]
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_token_deployed(
+        &mut self,
+        receipt_token: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        canonical_asset: CanonicalAssetId,
+        msg: Option<String>,
+        caller: AccountId,
+        deposit: NearToken,
+    ) -> PromiseOrValue<bool> {
+        if promise_succeeded(0) {
+            NepXChainEvent::AssetRegistered {
+                canonical_asset: canonical_asset.clone(),
+                receipt_token: receipt_token.clone(),
+            }.emit();
+            return PromiseOrValue::Promise(
+                Self::deliver(receipt_token, receiver_id, amount, canonical_asset, msg),
+            );
+        }
+        self.canonical_to_receipt.remove(&canonical_asset.key());
+        self.receipt_to_canonical.remove(&receipt_token);
+        self.asset_records.remove(&receipt_token);
+        if !deposit.is_zero() {
+            let _ = Promise::new(caller).transfer(deposit);
+        }
+        self.escrow_claim(receipt_token, receiver_id, amount, canonical_asset);
+        PromiseOrValue::Value(false)
+    }
+
+    fn escrow_claim(&mut self, receipt_token: AccountId, receiver_id: AccountId, amount: U128, canonical_asset: CanonicalAssetId) {
+        let claims = self.pending_claims.entry(receiver_id).or_default();
+        match claims.iter_mut().find(|c| c.receipt_token == receipt_token) {
+            Some(claim) => claim.amount = U128(claim.amount.0 + amount.0),
+            None => claims.push(PendingClaim { canonical_asset, receipt_token, amount }),
+        }
+    }
+
+    fn mint_receipt(receipt_token: &AccountId, receiver_id: &AccountId, amount: U128) -> Promise {
+        Promise::new(receipt_token.clone())
+            .function_call(
+                "mint".to_string(),
+                near_sdk::serde_json::json!({
+                    "account_id": receiver_id,
+                    "amount": amount
+                }).to_string().into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_MINT_TOKEN,
+            )
+    }
+
+    fn escrow_on_failure(receipt_token: AccountId, receiver_id: AccountId, amount: U128, canonical_asset: CanonicalAssetId) -> Promise {
+        Self::ext(env::current_account_id())
+            .with_static_gas(GAS_ON_MINT)
+            .on_mint(receipt_token, receiver_id, amount, canonical_asset)
+    }
+
+    fn code_hash(code: &[u8]) -> Option<Base58CryptoHash> {
+        (!code.is_empty()).then(|| env::sha256_array(code).into())
+    }
+
+    fn verify_committee(&self, proof: &BridgeProof) {
+        let committee_proof = proof.committee_proof.as_ref().expect("Missing committee proof");
+        self.committee.verify(committee_proof, &proof.proof_data);
+    }
+
+    fn assert_chain_enabled(&self, chain_id: &ChainId) -> &ChainConfig {
+        let config = self.supported_chains.get(chain_id).expect("Unsupported chain");
+        require!(config.enabled, "Chain is disabled");
+        config
+    }
+
+    fn replay(&self, path: TransferPath) -> &NonceTracker {
+        match path {
+            TransferPath::Committee => &self.replay,
+            TransferPath::EthProof => &self.eth_replay,
+        }
+    }
+
+    fn replay_mut(&mut self, path: TransferPath) -> &mut NonceTracker {
+        match path {
+            TransferPath::Committee => &mut self.replay,
+            TransferPath::EthProof => &mut self.eth_replay,
+        }
+    }
+
+    /// Whether `mark_processed` would accept `nonce` from `source_chain`.
+    pub(crate) fn can_process(&self, source_chain: &ChainId, nonce: u128, path: TransferPath) -> bool {
+        !self.is_transfer_processed(source_chain.clone(), U128(nonce), Some(path))
+            && self
+                .next_nonces
+                .get(&(source_chain.clone(), path))
+                .is_none_or(|next_nonce| *next_nonce == nonce)
+    }
+
+    fn mark_processed(&mut self, source_chain: &ChainId, nonce: u128, path: TransferPath) {
+        // Committee-signed nonces start at 1. Locker nonces on Ethereum can
+        // start at 0, so the eth-proof space starts from the default.
+        if path == TransferPath::Committee {
+            self.replay_mut(path).start_at(source_chain, 1);
+        }
+        require!(
+            !self.is_transfer_processed(source_chain.clone(), U128(nonce), Some(path))
+                && self.replay_mut(path).mark(source_chain, nonce),
+            "Transfer already processed"
+        );
+        if let Some(next_nonce) = self.next_nonces.get_mut(&(source_chain.clone(), path)) {
+            require!(nonce == *next_nonce, "Unexpected nonce");
+            *next_nonce += 1;
+        }
+    }
+
+    /// Mints `payload.amount` of the asset's receipt token, deploying the token
+    /// first if needed, and delivers it to the receiver, calling
+    /// `ft_transfer_call` with `msg` when one is given. If a deployment fails
+    /// the attached deposit is refunded to `caller`.
+    fn process_payload(&mut self, payload: BridgePayload, msg: Option<String>, path: TransferPath, caller: AccountId) -> Promise {
+        self.assert_chain_enabled(&payload.source_chain);
+        self.mark_processed(&payload.source_chain, payload.nonce.0, path);
+
+        let source_tx_hash = payload.source_tx_hash;
+        let receiver_id = payload.receiver;
+        let amount = payload.amount;
+        let canonical_asset = payload.asset;
+        
+        if let Some(token_account) = self.receipt_token_for(&canonical_asset).cloned() {
+            self.record_locked(&token_account, amount);
+            NepXChainEvent::BridgeIn {
+                canonical_asset: canonical_asset.clone(),
+                amount,
+                receiver_id: receiver_id.clone(),
+                source_tx_hash,
+                receipt_token: token_account.clone(),
+            }.emit();
+
+            Self::deliver(token_account, receiver_id, amount, canonical_asset, msg)
+        } else {
+            let (token_account_id, deploy) = self.deploy_receipt_token(&canonical_asset);
+            self.record_locked(&token_account_id, amount);
+            NepXChainEvent::BridgeIn {
+                canonical_asset: canonical_asset.clone(),
+                amount,
+                receiver_id: receiver_id.clone(),
+                source_tx_hash,
+                receipt_token: token_account_id.clone(),
+            }.emit();
+
+            deploy.then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_ON_TOKEN_DEPLOYED)
+                    .on_token_deployed(
+                        token_account_id,
+                        receiver_id,
+                        amount,
+                        canonical_asset,
+                        msg,
+                        caller,
+                        env::attached_deposit(),
+                    ),
+            )
+        }
+    }
+
+    /// Maps `canonical_asset` to a new receipt token sub-account and returns
+    /// the batch that creates and initializes it. The attached deposit funds
+    /// the account.
+    fn deploy_receipt_token(&mut self, canonical_asset: &CanonicalAssetId) -> (AccountId, Promise) {
+        require!(!self.receipt_token_code.is_empty(), "Factory not initialized");
+        canonical_asset.validate().unwrap_or_else(|e| env::panic_str(&e));
+        let canonical_key = canonical_asset.key();
+        let sub_account_name = hex::encode(&env::sha256(canonical_key.as_bytes())[0..20]);
+        let token_account_id: AccountId = format!("{}.{}", sub_account_name, env::current_account_id()).parse().unwrap();
+
+        self.canonical_to_receipt.insert(canonical_key, token_account_id.clone());
+        self.receipt_to_canonical.insert(token_account_id.clone(), canonical_asset.clone());
+        self.set_asset_route(&token_account_id, self.get_bridge_route());
+
+        let deploy = Promise::new(token_account_id.clone())
+            .create_account()
+            .transfer(env::attached_deposit())
+            .deploy_contract(self.receipt_token_code.clone())
+            .function_call(
+                "new".to_string(),
+                near_sdk::serde_json::json!({
+                    "owner_id": env::current_account_id(),
+                    "metadata": {
+                        "spec": "ft-1.0.0",
+                        "name": format!("Bridged {}", canonical_asset.source_contract),
+                        "symbol": "xAsset",
+                        "decimals": 18 
+                    },
+                    "xc_metadata": ReceiptTokenMetadata {
+                        canonical_asset: canonical_asset.clone(),
+                        bridge_route: self.get_bridge_route(),
+                        bridge_routes: vec![AuthorizedRoute {
+                            route: self.get_bridge_route(),
+                            destination_chains: self.destination_chains(),
+                            supply: U128(0),
+                        }],
+                        total_locked: U128(0),
+                        nep141_token: token_account_id.clone()
+                    }
+                }).to_string().into_bytes(),
+                NearToken::from_yoctonear(0),
+                GAS_INIT_TOKEN,
+            );
+        (token_account_id, deploy)
+    }
+
+    /// The receipt token of `canonical_asset`, also found under its pre-CAIP-19
+    /// key until `migrate_asset_keys` has re-keyed it.
+    fn receipt_token_for(&self, canonical_asset: &CanonicalAssetId) -> Option<&AccountId> {
+        self.canonical_to_receipt
+            .get(&canonical_asset.key())
+            .or_else(|| self.canonical_to_receipt.get(&migration::legacy_asset_key(canonical_asset)))
     }
 
     fn assert_owner(&self) {
@@ -64,43 +628,6 @@ impl XChainBridge {
     fn assert_not_paused(&self) {
         require!(!self.paused, "Bridge is paused");
     }
-    
-    fn emit_bridge_in(canonical_asset: &CanonicalAssetId, amount: U128, receiver_id: &AccountId, source_tx_hash: &str, receipt_token: &AccountId) {
-        env::log_str(&format!(
-            "EVENT_JSON:{}",
-            near_sdk::serde_json::json!({
-                "standard": "nep_xchain",
-                "version": "1.0.0",
-                "event": "bridge_in",
-                "data": {
-                    "canonical_asset": canonical_asset,
-                    "amount": amount,
-                    "receiver_id": receiver_id,
-                    "source_tx_hash": source_tx_hash,
-                    "receipt_token": receipt_token
-                }
-            })
-        ));
-    }
-
-    fn emit_bridge_out(canonical_asset: &CanonicalAssetId, amount: U128, sender_id: &AccountId, destination_chain: &str, destination_address: &str, receipt_token: &AccountId) {
-        env::log_str(&format!(
-            "EVENT_JSON:{}",
-            near_sdk::serde_json::json!({
-                "standard": "nep_xchain",
-                "version": "1.0.0",
-                "event": "bridge_out",
-                "data": {
-                    "canonical_asset": canonical_asset,
-                    "amount": amount,
-                    "sender_id": sender_id,
-                    "destination_chain": destination_chain,
-                    "destination_address": destination_address,
-                    "receipt_token": receipt_token
-                }
-            })
-        ));
-    }
 }
 
 #[near]
@@ -112,85 +639,24 @@ impl XChainCore for XChainBridge {
         receiver_id: AccountId,
     ) -> Promise {
         self.assert_not_paused();
+        if proof.eth_proof.is_some() {
+            return self.request_eth_block_hash(proof, receiver_id);
+        }
+        self.verify_committee(&proof);
+        require!(
+            !self.legacy_proofs.contains(&env::sha256(&proof.proof_data)),
+            "Transfer already processed"
+        );
         
-        let proof_hash = env::sha256(&proof.proof_data);
-        require!(!self.processed_proofs.contains(&proof_hash), "Proof already used");
-        self.processed_proofs.insert(proof_hash);
-
-        let payload: xchain_core::BridgePayload = near_sdk::borsh::BorshDeserialize::try_from_slice(&proof.proof_data)
+        let TokenCallPayload { transfer: payload, msg } = TokenCallPayload::from_proof_data(&proof.proof_data)
             .expect("Failed to deserialize BridgePayload");
 
-        require!(payload.target_chain.contains("near"), "Wrong target chain");
+        require!(payload.target_chain == self.chain_id, "Wrong target chain");
+        require!(payload.asset.source_chain_id == payload.source_chain, "Asset source chain mismatch");
         require!(payload.receiver == receiver_id, "Receiver mismatch");
+        require!(payload.source_tx_hash == proof.source_tx_hash, "Source transaction mismatch");
 
-        let canonical_id_str = format!("{}:{}:{:?}", 
-            payload.asset.source_chain_id, 
-            payload.asset.source_contract, 
-            payload.asset.asset_standard
-        );
-        let amount = payload.amount;
-        let canonical_asset = payload.asset;
-        
-        if let Some(token_account) = self.canonical_to_receipt.get(&canonical_id_str) {
-            Self::emit_bridge_in(&canonical_asset, amount, &receiver_id, &proof.source_tx_hash, token_account);
-            
-            Promise::new(token_account.clone())
-                .function_call(
-                    "mint".to_string(),
-                    near_sdk::serde_json::json!({
-                        "account_id": receiver_id,
-                        "amount": amount
-                    }).to_string().into_bytes(),
-                    NearToken::from_yoctonear(0),
-                    GAS_MINT_TOKEN,
-                )
-        } else {
-            require!(!self.receipt_token_code.is_empty(), "Factory not initialized");
-            let sub_account_name = hex::encode(&env::sha256(canonical_id_str.as_bytes())[0..20]);
-            let token_account_id: AccountId = format!("{}.{}", sub_account_name, env::current_account_id()).parse().unwrap();
-            
-            self.canonical_to_receipt.insert(canonical_id_str.clone(), token_account_id.clone());
-            self.receipt_to_canonical.insert(token_account_id.clone(), canonical_asset.clone());
-
-            Self::emit_bridge_in(&canonical_asset, amount, &receiver_id, &proof.source_tx_hash, &token_account_id);
-
-            Promise::new(token_account_id.clone())
-                .create_account()
-                .transfer(env::attached_deposit())
-                .deploy_contract(self.receipt_token_code.clone())
-                .function_call(
-                    "new".to_string(),
-                    near_sdk::serde_json::json!({
-                        "owner_id": env::current_account_id(),
-                        "metadata": {
-                            "spec": "ft-1.0.0",
-                            "name": format!("Bridged {}", canonical_asset.source_contract),
-                            "symbol": "xAsset",
-                            "decimals": 18 
-                        },
-                        "xc_metadata": ReceiptTokenMetadata {
-                            canonical_asset: canonical_asset.clone(),
-                            bridge_route: self.get_bridge_route(),
-                            total_locked: amount,
-                            nep141_token: token_account_id.clone()
-                        }
-                    }).to_string().into_bytes(),
-                    NearToken::from_yoctonear(0),
-                    GAS_INIT_TOKEN,
-                )
-                .then(
-                    Promise::new(token_account_id)
-                        .function_call(
-                            "mint".to_string(),
-                            near_sdk::serde_json::json!({
-                                "account_id": receiver_id,
-                                "amount": amount
-                            }).to_string().into_bytes(),
-                            NearToken::from_yoctonear(0),
-                            GAS_MINT_TOKEN,
-                        )
-                )
-        }
+        self.process_payload(payload, msg, TransferPath::Committee, env::predecessor_account_id())
     }
 
     #[payable]
@@ -202,23 +668,17 @@ impl XChainCore for XChainBridge {
         destination_address: String,
     ) -> Promise {
         self.assert_not_paused();
-        
+        self.assert_chain_enabled(&destination_chain)
+            .address_format
+            .validate(&destination_address)
+            .unwrap_or_else(|e| env::panic_str(&e));
+
         let attached = env::attached_deposit();
         require!(attached >= BRIDGE_FEE, "Insufficient Fee");
-        let _ = Promise::new(self.treasury.clone()).transfer(attached);
 
         let asset = self.receipt_to_canonical.get(&token_id).expect("Token not managed by bridge").clone();
 
-        Self::emit_bridge_out(
-            &asset, 
-            amount, 
-            &env::predecessor_account_id(), 
-            &destination_chain, 
-            &destination_address, 
-            &token_id
-        );
-        
-        Promise::new(token_id)
+        Promise::new(token_id.clone())
             .function_call(
                 "burn".to_string(),
                 near_sdk::serde_json::json!({
@@ -228,6 +688,19 @@ impl XChainCore for XChainBridge {
                 NearToken::from_yoctonear(0),
                 GAS_MINT_TOKEN,
             )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_ON_BURN)
+                    .on_bridge_out_burn(
+                        token_id,
+                        amount,
+                        asset,
+                        env::predecessor_account_id(),
+                        destination_chain,
+                        destination_address,
+                        attached,
+                    ),
+            )
     }
 
     fn get_canonical_asset(&self, token_id: AccountId) -> Option<CanonicalAssetId> {
@@ -235,13 +708,15 @@ impl XChainCore for XChainBridge {
     }
 
     fn get_bridge_route(&self) -> BridgeRoute {
+        let current = self.committee.current();
         BridgeRoute {
             bridge_contract: env::current_account_id(),
-            security_model: SecurityModel::Committee { 
-                threshold: ((self.validators.len() * 2) / 3 + 1) as u8, 
-                size: self.validators.len() as u8 
+            security_model: SecurityModel::WeightedCommittee {
+                quorum: current.quorum,
+                total_weight: current.total_weight(),
+                size: current.validators.len() as u32,
             },
-            deployed_at: env::block_timestamp(),
+            deployed_at: self.deployed_at,
         }
     }
 
@@ -256,20 +731,49 @@ impl XChainCore for XChainBridge {
     
     fn set_paused(&mut self, paused: bool) {
         self.assert_owner();
-        self.paused = paused;
+        let old_paused = std::mem::replace(&mut self.paused, paused);
+        NepXChainEvent::Paused {
+            caller: env::predecessor_account_id(),
+            old_paused,
+            new_paused: paused,
+        }.emit();
     }
     
     fn is_paused(&self) -> bool {
         self.paused
     }
     
-    fn add_validator(&mut self, public_key: PublicKey) {
+    fn add_validator(
+        &mut self,
+        public_key: ValidatorKey,
+        weight: Option<u64>,
+        operator_id: Option<AccountId>,
+        name: Option<String>,
+    ) {
         self.assert_owner();
-        self.validators.insert(public_key);
+        self.assert_bonded(&public_key);
+        let weight = weight.unwrap_or(1);
+        self.committee.add_validator(ValidatorConfig {
+            public_key: public_key.clone(),
+            weight,
+            operator_id,
+            name,
+        });
+        self.record_route_change();
+        NepXChainEvent::ValidatorAdded {
+            caller: env::predecessor_account_id(),
+            public_key,
+            weight,
+        }.emit();
     }
     
-    fn remove_validator(&mut self, public_key: PublicKey) {
+    fn remove_validator(&mut self, public_key: ValidatorKey) {
         self.assert_owner();
-        self.validators.remove(&public_key);
+        self.committee.remove_validator(&public_key);
+        self.record_route_change();
+        NepXChainEvent::ValidatorRemoved {
+            caller: env::predecessor_account_id(),
+            public_key,
+        }.emit();
     }
 }
@@ -0,0 +1,98 @@
+use near_sdk::store::{IterableMap, IterableSet, LookupMap, Vector};
+use near_sdk::{env, near, AccountId, PublicKey, Timestamp};
+use near_sdk::json_types::U128;
+use xchain_core::committee::Committee;
+use xchain_core::nonces::NonceTracker;
+use xchain_core::{CanonicalAssetId, ChainId, ValidatorKey};
+
+use crate::{staking, XChainBridge, XChainBridgeExt};
+
+/// The originally deployed layout: processed proofs as a set of proof hashes
+/// and a flat set of validator keys.
+#[near(serializers = [borsh])]
+struct XChainBridgeV1 {
+    receipt_token_code: Vec<u8>,
+    canonical_to_receipt: IterableMap<String, AccountId>,
+    receipt_to_canonical: IterableMap<AccountId, CanonicalAssetId>,
+    processed_proofs: IterableSet<Vec<u8>>,
+    owner_id: AccountId,
+    validators: IterableSet<PublicKey>,
+    paused: bool,
+    treasury: AccountId,
+}
+
+#[near]
+impl XChainBridge {
+    /// Upgrades from the originally deployed layout. The validator set becomes
+    /// epoch 0 of the committee (an empty set accepts no proof until a
+    /// validator is added), and the proof hashes stay as `legacy_proofs`
+    /// so transfers processed before the upgrade cannot be replayed. The bridge
+    /// is left paused until the owner has run `migrate_asset_keys` and
+    /// configured the supported chains again.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(chain_id: ChainId, deployed_at: Option<Timestamp>) -> Self {
+        let mut old: XChainBridgeV1 = env::state_read().expect("No state to migrate");
+        let validators: Vec<ValidatorKey> = old.validators.iter().cloned().map(ValidatorKey::Near).collect();
+        old.validators.clear();
+        old.validators.flush();
+
+        let mut bridge = Self {
+            receipt_token_code: old.receipt_token_code,
+            canonical_to_receipt: old.canonical_to_receipt,
+            receipt_to_canonical: old.receipt_to_canonical,
+            legacy_proofs: old.processed_proofs,
+            next_nonces: LookupMap::new(b"o"),
+            owner_id: old.owner_id,
+            committee: Committee::new(b"v", validators),
+            paused: true,
+            treasury: old.treasury,
+            pending_claims: LookupMap::new(b"e"),
+            claim_delegates: LookupMap::new(b"g"),
+            bonds: LookupMap::new(b"b"),
+            unbonding_period: staking::DEFAULT_UNBONDING_PERIOD,
+            min_validator_bond: U128(staking::DEFAULT_MIN_VALIDATOR_BOND),
+            eth_prover: None,
+            replay: NonceTracker::new(b"n"),
+            eth_replay: NonceTracker::new(b"q"),
+            chain_id,
+            supported_chains: IterableMap::new(b"s"),
+            deployed_at: deployed_at.unwrap_or_else(env::block_timestamp),
+            route_history: Vector::new(b"h"),
+            asset_records: LookupMap::new(b"a"),
+        };
+        bridge.record_route_change();
+        bridge
+    }
+
+    /// Re-keys `canonical_to_receipt` entries for receipt tokens
+    /// `from_index..from_index + limit` from the old `Debug`-formatted key to
+    /// `CanonicalAssetId::key()` and returns the index to continue from. When
+    /// two old entries normalize to the same key the first one keeps it; the
+    /// other token stays bridgeable out through `receipt_to_canonical`.
+    pub fn migrate_asset_keys(&mut self, from_index: u32, limit: u32) -> u32 {
+        self.assert_owner();
+        let assets: Vec<(AccountId, CanonicalAssetId)> = self
+            .receipt_to_canonical
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(token, asset)| (token.clone(), asset.clone()))
+            .collect();
+        let next_index = from_index + assets.len() as u32;
+        for (token, asset) in assets {
+            let legacy_key = legacy_asset_key(&asset);
+            if self.canonical_to_receipt.get(&legacy_key) == Some(&token) {
+                self.canonical_to_receipt.remove(&legacy_key);
+            }
+            self.canonical_to_receipt.entry(asset.key()).or_insert(token);
+        }
+        next_index
+    }
+}
+
+/// The `canonical_to_receipt` key assets were stored under before
+/// `CanonicalAssetId::key()`.
+pub(crate) fn legacy_asset_key(asset: &CanonicalAssetId) -> String {
+    format!("{}:{}:{:?}", asset.source_chain_id, asset.source_contract, asset.asset_standard)
+}
@@ -0,0 +1,21 @@
+use near_sdk::{near, require, AccountId, Gas, NearToken, Promise};
+
+use crate::{XChainBridge, XChainBridgeExt};
+
+const GAS_REGISTER_RECEIPT: Gas = Gas::from_tgas(40);
+
+#[near]
+impl XChainBridge {
+    /// Registers one of this bridge's receipt tokens so the registry can
+    /// verify it against `get_canonical_asset`.
+    pub fn register_receipt_with_registry(&mut self, registry_id: AccountId, token_id: AccountId) -> Promise {
+        self.assert_owner();
+        require!(self.receipt_to_canonical.contains_key(&token_id), "Token not managed by bridge");
+        Promise::new(registry_id).function_call(
+            "register_receipt".to_string(),
+            near_sdk::serde_json::json!({ "token_id": token_id }).to_string().into_bytes(),
+            NearToken::from_yoctonear(0),
+            GAS_REGISTER_RECEIPT,
+        )
+    }
+}
@@ -0,0 +1,216 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near, require, AccountId, Gas, NearToken, Promise, Timestamp};
+use xchain_core::events::NepXChainEvent;
+use xchain_core::{promise_succeeded, promise_value, BridgeRoute, CanonicalAssetId, ChainId, SecurityModel, XChainCore};
+
+use crate::{XChainBridge, XChainBridgeExt, DEFAULT_PAGE_LIMIT};
+
+const GAS_SET_ROUTE: Gas = Gas::from_tgas(10);
+const GAS_ON_ASSET_SYNCED: Gas = Gas::from_tgas(5);
+const GAS_UPGRADE_TOKEN: Gas = Gas::from_tgas(100);
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct RouteChange {
+    pub security_model: SecurityModel,
+    pub changed_at: Timestamp,
+}
+
+/// This bridge's view of one receipt token: the route it last set on the
+/// token and the amount locked on the source chain through this bridge.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct AssetRecord {
+    pub bridge_route: BridgeRoute,
+    pub total_locked: U128,
+}
+
+#[near]
+impl XChainBridge {
+    pub fn get_route_history(&self, from_index: Option<u32>, limit: Option<u32>) -> Vec<RouteChange> {
+        self.route_history
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Pushes the bridge's current route and enabled destination chains into
+    /// `token_id`'s `xc_metadata`. Anyone can call it after either changed.
+    pub fn refresh_bridge_route(&mut self, token_id: AccountId) -> Promise {
+        require!(self.receipt_to_canonical.contains_key(&token_id), "Token not managed by bridge");
+        let bridge_route = self.get_bridge_route();
+        let update = Self::call_token(token_id.clone(), "xc_update_route", near_sdk::serde_json::json!({
+            "bridge_route": bridge_route,
+            "destination_chains": self.destination_chains(),
+        }));
+        if self.asset_records.contains_key(&token_id) {
+            self.set_asset_route(&token_id, bridge_route);
+            return update;
+        }
+        // Tokens deployed before the bridge kept asset records were only ever
+        // minted through this bridge and did not count `total_locked`, so
+        // their total supply is what was locked through this bridge.
+        update
+            .then(Self::call_token(token_id.clone(), "ft_total_supply", near_sdk::serde_json::json!({})))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_ON_ASSET_SYNCED)
+                    .on_asset_synced(token_id, bridge_route),
+            )
+    }
+
+    #[private]
+    pub fn on_asset_synced(&mut self, token_id: AccountId, bridge_route: BridgeRoute) -> bool {
+        let total_locked = promise_value(0).and_then(|result| near_sdk::serde_json::from_slice::<U128>(&result).ok());
+        let Some(total_locked) = total_locked else {
+            return false;
+        };
+        self.asset_records
+            .entry(token_id)
+            .or_insert(AssetRecord { bridge_route, total_locked });
+        true
+    }
+
+    /// Completes `bridge_out` once the tokens are burned: the fee goes to the
+    /// treasury and `BridgeOut` is emitted. If the burn failed the fee is
+    /// refunded to the sender and nothing is emitted.
+    #[private]
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_bridge_out_burn(
+        &mut self,
+        token_id: AccountId,
+        amount: U128,
+        canonical_asset: CanonicalAssetId,
+        sender_id: AccountId,
+        destination_chain: ChainId,
+        destination_address: String,
+        fee: NearToken,
+    ) -> bool {
+        if !promise_succeeded(0) {
+            let _ = Promise::new(sender_id).transfer(fee);
+            return false;
+        }
+        if let Some(record) = self.asset_records.get_mut(&token_id) {
+            record.total_locked = U128(record.total_locked.0.saturating_sub(amount.0));
+        }
+        let _ = Promise::new(self.treasury.clone()).transfer(fee);
+        NepXChainEvent::BridgeOut {
+            canonical_asset,
+            amount,
+            sender_id,
+            destination_chain,
+            destination_address,
+            receipt_token: token_id,
+        }.emit();
+        true
+    }
+
+    /// Deploys the current receipt token code to `token_id` through its
+    /// `upgrade` method, which then runs `migrate`.
+    pub fn upgrade_receipt_token(&mut self, token_id: AccountId) -> Promise {
+        self.assert_owner();
+        require!(self.receipt_to_canonical.contains_key(&token_id), "Token not managed by bridge");
+        require!(!self.receipt_token_code.is_empty(), "Factory not initialized");
+        NepXChainEvent::ReceiptTokenUpgraded {
+            caller: env::predecessor_account_id(),
+            receipt_token: token_id.clone(),
+            code_hash: env::sha256_array(&self.receipt_token_code).into(),
+        }.emit();
+        Promise::new(token_id).function_call(
+            "upgrade".to_string(),
+            self.receipt_token_code.clone(),
+            NearToken::from_yoctonear(0),
+            GAS_UPGRADE_TOKEN,
+        )
+    }
+
+    /// Authorizes another bridge to mint and burn a receipt token this bridge
+    /// deployed.
+    pub fn add_token_route(
+        &mut self,
+        token_id: AccountId,
+        bridge_route: BridgeRoute,
+        destination_chains: Vec<ChainId>,
+    ) -> Promise {
+        self.assert_owner();
+        Self::call_token(token_id, "xc_add_route", near_sdk::serde_json::json!({
+            "bridge_route": bridge_route,
+            "destination_chains": destination_chains,
+        }))
+    }
+
+    pub fn remove_token_route(&mut self, token_id: AccountId, bridge_contract: AccountId) -> Promise {
+        self.assert_owner();
+        Self::call_token(token_id, "xc_remove_route", near_sdk::serde_json::json!({
+            "bridge_contract": bridge_contract,
+        }))
+    }
+
+    /// Adopts a receipt token deployed by another bridge that authorized this
+    /// one as a route, so `bridge_in`/`bridge_out` use it for `canonical_asset`.
+    pub fn import_receipt_token(&mut self, canonical_asset: CanonicalAssetId, token_id: AccountId) {
+        self.assert_owner();
+        canonical_asset.validate().unwrap_or_else(|e| env::panic_str(&e));
+        require!(self.receipt_token_for(&canonical_asset).is_none(), "Asset already has a receipt token");
+        require!(!self.receipt_to_canonical.contains_key(&token_id), "Token already managed by bridge");
+        self.canonical_to_receipt.insert(canonical_asset.key(), token_id.clone());
+        self.receipt_to_canonical.insert(token_id.clone(), canonical_asset.clone());
+        self.set_asset_route(&token_id, self.get_bridge_route());
+        NepXChainEvent::AssetRegistered {
+            canonical_asset,
+            receipt_token: token_id,
+        }.emit();
+    }
+}
+
+impl XChainBridge {
+    pub(crate) fn destination_chains(&self) -> Vec<ChainId> {
+        self.supported_chains
+            .iter()
+            .filter(|(_, config)| config.enabled)
+            .map(|(chain_id, _)| chain_id.clone())
+            .collect()
+    }
+
+    /// Records `bridge_route` as the token's route, keeping its locked total.
+    pub(crate) fn set_asset_route(&mut self, token_id: &AccountId, bridge_route: BridgeRoute) {
+        match self.asset_records.get_mut(token_id) {
+            Some(record) => record.bridge_route = bridge_route,
+            None => {
+                self.asset_records.insert(token_id.clone(), AssetRecord { bridge_route, total_locked: U128(0) });
+            }
+        }
+    }
+
+    pub(crate) fn record_locked(&mut self, token_id: &AccountId, amount: U128) {
+        if let Some(record) = self.asset_records.get_mut(token_id) {
+            record.total_locked = U128(record.total_locked.0 + amount.0);
+        }
+    }
+
+    fn call_token(token_id: AccountId, method: &str, args: near_sdk::serde_json::Value) -> Promise {
+        Promise::new(token_id).function_call(
+            method.to_string(),
+            args.to_string().into_bytes(),
+            NearToken::from_yoctonear(0),
+            GAS_SET_ROUTE,
+        )
+    }
+
+    /// Appends the current security model to the route history if it differs
+    /// from the last recorded one.
+    pub(crate) fn record_route_change(&mut self) {
+        let security_model = self.get_bridge_route().security_model;
+        let last = self.route_history.len().checked_sub(1).and_then(|i| self.route_history.get(i));
+        if last.is_some_and(|change| change.security_model == security_model) {
+            return;
+        }
+        self.route_history.push(RouteChange {
+            security_model: security_model.clone(),
+            changed_at: env::block_timestamp(),
+        });
+        NepXChainEvent::BridgeRouteChanged { security_model }.emit();
+    }
+}
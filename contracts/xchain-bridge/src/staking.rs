@@ -0,0 +1,233 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near, require, AccountId, NearToken, Promise, Timestamp};
+use xchain_core::events::NepXChainEvent;
+use xchain_core::committee::verify_signature;
+use xchain_core::{TokenCallPayload, ValidatorKey};
+
+use crate::{XChainBridge, XChainBridgeExt};
+
+pub const DEFAULT_UNBONDING_PERIOD: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+pub const DEFAULT_MIN_VALIDATOR_BOND: u128 = 1_000_000_000_000_000_000_000_000;
+
+const BOND_DOMAIN: &[u8] = b"nep_xchain_bond";
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct ValidatorBond {
+    pub owner_id: AccountId,
+    pub staked: U128,
+    pub unbonding: U128,
+    pub unlock_at: Option<Timestamp>,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct SignedPayload {
+    pub epoch: u64,
+    pub proof_data: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+#[near]
+impl XChainBridge {
+    /// Adds the attached deposit to the bond of `public_key`. Opening a bond
+    /// requires `signature`, made by the key over `bond_message`, so only the
+    /// key holder can choose the bond's owner.
+    #[payable]
+    pub fn bond(&mut self, public_key: ValidatorKey, signature: Option<Vec<u8>>) {
+        let caller = env::predecessor_account_id();
+        let amount = env::attached_deposit().as_yoctonear();
+        require!(amount > 0, "Attach NEAR to bond");
+
+        if !self.bonds.contains_key(&public_key) {
+            let signature = signature.expect("Missing key signature");
+            require!(
+                verify_signature(&public_key, &signature, &bond_message(&caller)),
+                "Invalid key signature"
+            );
+        }
+        let bond = self.bonds.entry(public_key.clone()).or_insert_with(|| ValidatorBond {
+            owner_id: caller.clone(),
+            staked: U128(0),
+            unbonding: U128(0),
+            unlock_at: None,
+        });
+        require!(bond.owner_id == caller, "Bond belongs to another account");
+        bond.staked = U128(bond.staked.0 + amount);
+        let total_staked = bond.staked;
+
+        NepXChainEvent::ValidatorBonded {
+            caller,
+            public_key,
+            amount: U128(amount),
+            total_staked,
+        }.emit();
+    }
+
+    /// Moves `amount` of the stake into unbonding. The whole unbonding amount
+    /// unlocks together; a new unbond never brings the unlock time forward.
+    pub fn unbond(&mut self, public_key: ValidatorKey, amount: U128) {
+        let caller = env::predecessor_account_id();
+        let unlock_at = env::block_timestamp() + self.unbonding_period;
+        let min_bond = self.min_validator_bond.0;
+        let is_validator = self.committee.is_validator(&public_key);
+
+        let bond = self.bonds.get_mut(&public_key).expect("No bond for validator");
+        require!(bond.owner_id == caller, "Unauthorized");
+        require!(amount.0 > 0 && amount.0 <= bond.staked.0, "Invalid unbond amount");
+        require!(
+            !is_validator || bond.staked.0 - amount.0 >= min_bond,
+            "Active validators must keep the minimum bond"
+        );
+        bond.staked = U128(bond.staked.0 - amount.0);
+        bond.unbonding = U128(bond.unbonding.0 + amount.0);
+        let unlock_at = bond.unlock_at.map_or(unlock_at, |previous| previous.max(unlock_at));
+        bond.unlock_at = Some(unlock_at);
+
+        NepXChainEvent::ValidatorUnbonding {
+            caller,
+            public_key,
+            amount,
+            unlock_at,
+        }.emit();
+    }
+
+    pub fn withdraw_stake(&mut self, public_key: ValidatorKey) -> Promise {
+        let caller = env::predecessor_account_id();
+        let bond = self.bonds.get_mut(&public_key).expect("No bond for validator");
+        require!(bond.owner_id == caller, "Unauthorized");
+        require!(bond.unbonding.0 > 0, "Nothing to withdraw");
+        require!(
+            bond.unlock_at.is_some_and(|unlock_at| env::block_timestamp() >= unlock_at),
+            "Stake is still unbonding"
+        );
+
+        let amount = std::mem::replace(&mut bond.unbonding, U128(0));
+        bond.unlock_at = None;
+        if bond.staked.0 == 0 {
+            self.bonds.remove(&public_key);
+        }
+
+        NepXChainEvent::StakeWithdrawn {
+            caller: caller.clone(),
+            public_key,
+            amount,
+        }.emit();
+
+        Promise::new(caller).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    /// Slashes the whole bond of `public_key` to the treasury and removes it from
+    /// the committee when it signed two different payloads for the same
+    /// `(source_chain, source_tx_hash, nonce)`. A key that was already slashed
+    /// cannot be reported again.
+    pub fn report_equivocation(
+        &mut self,
+        public_key: ValidatorKey,
+        first: SignedPayload,
+        second: SignedPayload,
+    ) -> Promise {
+        for signed in [&first, &second] {
+            require!(
+                self.committee.is_signed_by(signed.epoch, &public_key, &signed.signature, &signed.proof_data),
+                "Invalid validator signature"
+            );
+        }
+
+        let first_payload = decode_payload(&first.proof_data);
+        let second_payload = decode_payload(&second.proof_data);
+        let (first_transfer, second_transfer) = (&first_payload.transfer, &second_payload.transfer);
+        require!(
+            first_transfer.source_chain == second_transfer.source_chain
+                && first_transfer.source_tx_hash == second_transfer.source_tx_hash
+                && first_transfer.nonce == second_transfer.nonce,
+            "Payloads are for different transfers"
+        );
+        require!(
+            near_sdk::borsh::to_vec(&first_payload).unwrap() != near_sdk::borsh::to_vec(&second_payload).unwrap(),
+            "Payloads do not conflict"
+        );
+
+        require!(
+            self.bonds.contains_key(&public_key) || self.committee.is_validator(&public_key),
+            "Validator already slashed"
+        );
+        let amount = self
+            .bonds
+            .remove(&public_key)
+            .map_or(0, |bond| bond.staked.0 + bond.unbonding.0);
+        if self.committee.is_validator(&public_key) {
+            self.committee.remove_validator(&public_key);
+            self.record_route_change();
+        }
+
+        NepXChainEvent::ValidatorSlashed {
+            reporter: env::predecessor_account_id(),
+            public_key,
+            amount: U128(amount),
+            source_tx_hash: first_transfer.source_tx_hash.clone(),
+            nonce: first_transfer.nonce,
+        }.emit();
+
+        Promise::new(self.treasury.clone()).transfer(NearToken::from_yoctonear(amount))
+    }
+
+    pub fn set_unbonding_period(&mut self, unbonding_period: u64) {
+        self.assert_owner();
+        let old_unbonding_period = std::mem::replace(&mut self.unbonding_period, unbonding_period);
+        NepXChainEvent::UnbondingPeriodChanged {
+            caller: env::predecessor_account_id(),
+            old_unbonding_period,
+            new_unbonding_period: unbonding_period,
+        }.emit();
+    }
+
+    pub fn set_min_validator_bond(&mut self, min_validator_bond: U128) {
+        self.assert_owner();
+        require!(min_validator_bond.0 > 0, "Minimum bond must be positive");
+        let old_min_bond = std::mem::replace(&mut self.min_validator_bond, min_validator_bond);
+        NepXChainEvent::MinValidatorBondChanged {
+            caller: env::predecessor_account_id(),
+            old_min_bond,
+            new_min_bond: min_validator_bond,
+        }.emit();
+    }
+
+    pub fn get_bond(&self, public_key: ValidatorKey) -> Option<ValidatorBond> {
+        self.bonds.get(&public_key).cloned()
+    }
+
+    pub fn get_unbonding_period(&self) -> u64 {
+        self.unbonding_period
+    }
+
+    pub fn get_min_validator_bond(&self) -> U128 {
+        self.min_validator_bond
+    }
+
+    /// The message a validator key signs to open a bond owned by `owner_id`.
+    pub fn get_bond_message(&self, owner_id: AccountId) -> Vec<u8> {
+        bond_message(&owner_id)
+    }
+}
+
+impl XChainBridge {
+    pub(crate) fn assert_bonded(&self, public_key: &ValidatorKey) {
+        let staked = self.bonds.get(public_key).map_or(0, |bond| bond.staked.0);
+        require!(staked >= self.min_validator_bond.0, "Validator bond below minimum");
+    }
+}
+
+// sha256 of the domain, the bond owner and this bridge, so a signature cannot
+// be reused for another owner or another bridge.
+fn bond_message(owner_id: &AccountId) -> Vec<u8> {
+    let mut data = BOND_DOMAIN.to_vec();
+    data.extend(near_sdk::borsh::to_vec(&(owner_id.clone(), env::current_account_id())).expect("Failed to serialize bond owner"));
+    env::sha256(&data)
+}
+
+// A plain payload and a call payload with the same transfer conflict: the
+// latter redirects the tokens through `msg`.
+fn decode_payload(proof_data: &[u8]) -> TokenCallPayload {
+    TokenCallPayload::from_proof_data(proof_data).expect("Failed to deserialize BridgePayload")
+}
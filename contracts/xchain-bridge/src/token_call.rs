@@ -0,0 +1,169 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::{self, json};
+use near_sdk::{env, near, AccountId, Gas, NearToken, Promise, PromiseOrValue};
+use xchain_core::{promise_succeeded, promise_value, CanonicalAssetId};
+
+use crate::{XChainBridge, XChainBridgeExt};
+
+const GAS_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_FT_TRANSFER_CALL: Gas = Gas::from_tgas(60);
+const GAS_BURN: Gas = Gas::from_tgas(10);
+const GAS_ON_REFUND: Gas = Gas::from_tgas(30);
+const GAS_ON_REFUND_BURN: Gas = Gas::from_tgas(5);
+const GAS_STORAGE_DEPOSIT: Gas = Gas::from_tgas(10);
+const GAS_ON_TOKEN_CALL: Gas = Gas::from_tgas(50);
+const GAS_ON_MINT_FOR_CALL: Gas = Gas::from_tgas(130);
+const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
+const STORAGE_DEPOSIT: NearToken = NearToken::from_yoctonear(1_250_000_000_000_000_000_000);
+
+#[near]
+impl XChainBridge {
+    /// Forwards tokens minted to the bridge to the receiver with
+    /// `ft_transfer_call`. An undelivered mint is escrowed like a plain one.
+    #[private]
+    pub fn on_mint_for_call(
+        &mut self,
+        receipt_token: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        canonical_asset: CanonicalAssetId,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        if !promise_succeeded(0) {
+            self.escrow_claim(receipt_token, receiver_id, amount, canonical_asset);
+            return PromiseOrValue::Value(U128(0));
+        }
+
+        let transfer_call = Promise::new(receipt_token.clone()).function_call(
+            "ft_transfer_call".to_string(),
+            json!({
+                "receiver_id": receiver_id,
+                "amount": amount,
+                "msg": msg,
+            }).to_string().into_bytes(),
+            ONE_YOCTO,
+            GAS_FT_TRANSFER_CALL,
+        );
+        PromiseOrValue::Promise(transfer_call.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_ON_TOKEN_CALL)
+                .on_token_call(receipt_token, receiver_id, amount, canonical_asset),
+        ))
+    }
+
+    /// Returns the amount the receiver used. Whatever it did not use, or all of
+    /// it if the call failed, is refunded to the receiver with `ft_transfer`.
+    #[private]
+    pub fn on_token_call(
+        &mut self,
+        receipt_token: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        canonical_asset: CanonicalAssetId,
+    ) -> U128 {
+        let used = promise_value(0)
+            .and_then(|result| serde_json::from_slice::<U128>(&result).ok())
+            .map_or(0, |used| used.0);
+        let unused = U128(amount.0.saturating_sub(used));
+        if unused.0 > 0 {
+            let _ = Promise::new(receipt_token.clone())
+                .function_call(
+                    "ft_transfer".to_string(),
+                    json!({
+                        "receiver_id": receiver_id,
+                        "amount": unused,
+                    }).to_string().into_bytes(),
+                    ONE_YOCTO,
+                    GAS_FT_TRANSFER,
+                )
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_ON_REFUND)
+                        .on_refund(receipt_token, receiver_id, unused, canonical_asset),
+                );
+        }
+        U128(used)
+    }
+
+    /// If the refund could not be delivered (e.g. the receiver is not
+    /// registered with the token), the bridge burns the tokens it still holds
+    /// and escrows the amount as a pending claim once the burn went through.
+    #[private]
+    pub fn on_refund(
+        &mut self,
+        receipt_token: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        canonical_asset: CanonicalAssetId,
+    ) -> PromiseOrValue<bool> {
+        if promise_succeeded(0) {
+            return PromiseOrValue::Value(true);
+        }
+        let burn = Promise::new(receipt_token.clone()).function_call(
+            "burn".to_string(),
+            json!({
+                "account_id": env::current_account_id(),
+                "amount": amount,
+            }).to_string().into_bytes(),
+            NearToken::from_yoctonear(0),
+            GAS_BURN,
+        );
+        PromiseOrValue::Promise(burn.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_ON_REFUND_BURN)
+                .on_refund_burn(receipt_token, receiver_id, amount, canonical_asset),
+        ))
+    }
+
+    /// Escrows a refund the bridge burned. If the burn failed the tokens stay
+    /// with the bridge and nothing is escrowed, so they cannot be paid twice.
+    #[private]
+    pub fn on_refund_burn(
+        &mut self,
+        receipt_token: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        canonical_asset: CanonicalAssetId,
+    ) -> bool {
+        if !promise_succeeded(0) {
+            return false;
+        }
+        self.escrow_claim(receipt_token, receiver_id, amount, canonical_asset);
+        false
+    }
+}
+
+impl XChainBridge {
+    /// Mints `amount` to the receiver, or for a token+call transfer to the
+    /// bridge, which then forwards it with `ft_transfer_call`. The bridge pays
+    /// for its own storage on the token first; the token refunds the deposit
+    /// when the bridge is already registered.
+    pub(crate) fn deliver(
+        receipt_token: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        canonical_asset: CanonicalAssetId,
+        msg: Option<String>,
+    ) -> Promise {
+        let Some(msg) = msg else {
+            return Self::mint_receipt(&receipt_token, &receiver_id, amount)
+                .then(Self::escrow_on_failure(receipt_token, receiver_id, amount, canonical_asset));
+        };
+        Promise::new(receipt_token.clone())
+            .function_call(
+                "storage_deposit".to_string(),
+                json!({
+                    "account_id": env::current_account_id(),
+                    "registration_only": true,
+                }).to_string().into_bytes(),
+                STORAGE_DEPOSIT,
+                GAS_STORAGE_DEPOSIT,
+            )
+            .then(Self::mint_receipt(&receipt_token, &env::current_account_id(), amount))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_ON_MINT_FOR_CALL)
+                    .on_mint_for_call(receipt_token, receiver_id, amount, canonical_asset, msg),
+            )
+    }
+}
@@ -0,0 +1,260 @@
+use near_sdk::{env, AccountId};
+
+use crate::AddressFormat;
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+const BITCOIN_HRPS: [&str; 3] = ["bc", "tb", "bcrt"];
+
+impl AddressFormat {
+    /// Checks that `address` is a well-formed address for this chain family.
+    pub fn validate(&self, address: &str) -> Result<(), String> {
+        match self {
+            Self::Evm => validate_evm(address),
+            Self::Solana => validate_solana(address),
+            Self::Bitcoin => validate_bitcoin(address),
+            Self::Near => address
+                .parse::<AccountId>()
+                .map(|_| ())
+                .map_err(|e| format!("Invalid NEAR account: {}", e)),
+            Self::Cosmos { prefix } => validate_cosmos(address, prefix),
+        }
+    }
+}
+
+/// `0x` followed by 40 hex digits. Mixed-case addresses must carry a valid
+/// EIP-55 checksum; all-lowercase or all-uppercase ones are accepted as is.
+pub fn validate_evm(address: &str) -> Result<(), String> {
+    let hex_part = address
+        .strip_prefix("0x")
+        .filter(|h| h.len() == 40 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or("Invalid EVM address")?;
+
+    let has_lower = hex_part.bytes().any(|b| b.is_ascii_lowercase());
+    let has_upper = hex_part.bytes().any(|b| b.is_ascii_uppercase());
+    if !(has_lower && has_upper) {
+        return Ok(());
+    }
+
+    let hash = env::keccak256_array(hex_part.to_ascii_lowercase().as_bytes());
+    let checksum_ok = hex_part.bytes().enumerate().all(|(i, b)| {
+        let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+        !b.is_ascii_alphabetic() || b.is_ascii_uppercase() == (nibble >= 8)
+    });
+    if checksum_ok {
+        Ok(())
+    } else {
+        Err("Invalid EIP-55 checksum".to_string())
+    }
+}
+
+/// A base58 encoded 32-byte public key.
+pub fn validate_solana(address: &str) -> Result<(), String> {
+    match base58_decode(address) {
+        Some(bytes) if bytes.len() == 32 => Ok(()),
+        _ => Err("Invalid Solana address".to_string()),
+    }
+}
+
+/// A segwit address (BIP-173/BIP-350) on mainnet, testnet or regtest.
+pub fn validate_bitcoin(address: &str) -> Result<(), String> {
+    let (hrp, data, constant) = bech32_decode(address).ok_or("Invalid Bitcoin address")?;
+    require_ok(BITCOIN_HRPS.contains(&hrp.as_str()), "Invalid Bitcoin network prefix")?;
+    let (version, program) = data.split_first().ok_or("Invalid Bitcoin address")?;
+    let program = convert_bits(program).ok_or("Invalid Bitcoin address")?;
+    let valid = match version {
+        0 => constant == BECH32_CONST && (program.len() == 20 || program.len() == 32),
+        1..=16 => constant == BECH32M_CONST && (2..=40).contains(&program.len()),
+        _ => false,
+    };
+    require_ok(valid, "Invalid Bitcoin witness program")
+}
+
+/// A bech32 address with the chain's human-readable `prefix`.
+pub fn validate_cosmos(address: &str, prefix: &str) -> Result<(), String> {
+    let (hrp, data, constant) = bech32_decode(address).ok_or("Invalid Cosmos address")?;
+    require_ok(hrp == prefix, "Wrong Cosmos address prefix")?;
+    let bytes = convert_bits(&data).ok_or("Invalid Cosmos address")?;
+    require_ok(
+        constant == BECH32_CONST && (bytes.len() == 20 || bytes.len() == 32),
+        "Invalid Cosmos address",
+    )
+}
+
+fn require_ok(condition: bool, message: &str) -> Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(message.to_string())
+    }
+}
+
+fn base58_decode(s: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in s.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    bytes.extend(s.bytes().take_while(|c| *c == b'1').map(|_| 0));
+    bytes.reverse();
+    Some(bytes)
+}
+
+// Returns the lowercase HRP, the 5-bit data without checksum and the checksum
+// constant (bech32 or bech32m).
+fn bech32_decode(s: &str) -> Option<(String, Vec<u8>, u32)> {
+    if s.len() > 90 || (s.bytes().any(|b| b.is_ascii_lowercase()) && s.bytes().any(|b| b.is_ascii_uppercase())) {
+        return None;
+    }
+    let s = s.to_ascii_lowercase();
+    let (hrp, data) = s.rsplit_once('1')?;
+    if hrp.is_empty() || data.len() < 6 || !hrp.bytes().all(|b| (33..=126).contains(&b)) {
+        return None;
+    }
+    let data: Vec<u8> = data
+        .bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|a| *a == c).map(|v| v as u8))
+        .collect::<Option<_>>()?;
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 0x1f));
+    values.extend(&data);
+    let constant = bech32_polymod(&values);
+    if constant != BECH32_CONST && constant != BECH32M_CONST {
+        return None;
+    }
+    Some((hrp.to_string(), data[..data.len() - 6].to_vec(), constant))
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+    values.iter().fold(1u32, |chk, v| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(*v);
+        (0..5).fold(chk, |chk, i| if (top >> i) & 1 == 1 { chk ^ GENERATOR[i] } else { chk })
+    })
+}
+
+// Regroups 5-bit bech32 data into bytes, rejecting non-zero padding.
+fn convert_bits(data: &[u8]) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut out = Vec::with_capacity(data.len() * 5 / 8);
+    for v in data {
+        acc = ((acc << 5) | u32::from(*v)) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || (acc << (8 - bits)) & 0xff != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(format: AddressFormat, cases: &[(&str, Result<(), &str>)]) {
+        for &(address, expected) in cases {
+            let expected = expected.map_err(str::to_string);
+            assert_eq!(format.validate(address), expected, "{:?} {}", format, address);
+        }
+    }
+
+    #[test]
+    fn evm_addresses() {
+        check(AddressFormat::Evm, &[
+            // EIP-55 test vectors.
+            ("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", Ok(())),
+            ("0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359", Ok(())),
+            ("0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB", Ok(())),
+            ("0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb", Ok(())),
+            ("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed", Ok(())),
+            ("0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED", Ok(())),
+            ("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD", Err("Invalid EIP-55 checksum")),
+            ("0x5aAEb6053F3E94C9b9A09f33669435E7Ef1BeAed", Err("Invalid EIP-55 checksum")),
+            ("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", Err("Invalid EVM address")),
+            ("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA", Err("Invalid EVM address")),
+            ("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAedd", Err("Invalid EVM address")),
+            ("0x5gAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", Err("Invalid EVM address")),
+            ("", Err("Invalid EVM address")),
+        ]);
+    }
+
+    #[test]
+    fn solana_addresses() {
+        check(AddressFormat::Solana, &[
+            ("11111111111111111111111111111111", Ok(())),
+            ("So11111111111111111111111111111111111111112", Ok(())),
+            ("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA", Ok(())),
+            ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", Ok(())),
+            // 31 and 23 bytes.
+            ("1111111111111111111111111111111", Err("Invalid Solana address")),
+            ("5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp", Err("Invalid Solana address")),
+            // `0`, `O`, `I` and `l` are not in the alphabet.
+            ("So11111111111111111111111111111111111111110", Err("Invalid Solana address")),
+            ("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DO", Err("Invalid Solana address")),
+            ("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5Dl", Err("Invalid Solana address")),
+            ("", Err("Invalid Solana address")),
+        ]);
+    }
+
+    #[test]
+    fn bitcoin_addresses() {
+        check(AddressFormat::Bitcoin, &[
+            // BIP-173 and BIP-350 test vectors.
+            ("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4", Ok(())),
+            ("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", Ok(())),
+            ("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7", Ok(())),
+            ("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0", Ok(())),
+            ("bcrt1qqqqsyqcyq5rqwzqfpg9scrgwpugpzysnard0ew", Ok(())),
+            ("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5", Err("Invalid Bitcoin address")),
+            ("bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", Err("Invalid Bitcoin address")),
+            ("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", Err("Invalid Bitcoin address")),
+            ("ltc1qqqqsyqcyq5rqwzqfpg9scrgwpugpzysn3s44dy", Err("Invalid Bitcoin network prefix")),
+            // Version 0 with a bech32m checksum, version 1 with bech32.
+            ("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh", Err("Invalid Bitcoin witness program")),
+            ("bc1pqqqsyqcyq5rqwzqfpg9scrgwpugpzysn7jc6cl", Err("Invalid Bitcoin witness program")),
+        ]);
+    }
+
+    #[test]
+    fn cosmos_addresses() {
+        let cosmos = AddressFormat::Cosmos { prefix: "cosmos".to_string() };
+        check(cosmos, &[
+            ("cosmos1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnrk363e", Ok(())),
+            ("COSMOS1QQQSYQCYQ5RQWZQFPG9SCRGWPUGPZYSNRK363E", Ok(())),
+            ("osmo1qqqsyqcyq5rqwzqfpg9scrgwpugpzysntdz28t", Err("Wrong Cosmos address prefix")),
+            ("cosmos1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnk2pk5m", Err("Invalid Cosmos address")),
+            ("cosmos1qqqsyqcyq5rqwzqfpg9scrgwpugpzysnrk363f", Err("Invalid Cosmos address")),
+            ("cosmos", Err("Invalid Cosmos address")),
+        ]);
+    }
+
+    #[test]
+    fn near_accounts() {
+        check(AddressFormat::Near, &[
+            ("alice.near", Ok(())),
+            ("a1b2c3d4.xchain-bridge.testnet", Ok(())),
+            ("98793cd91a3f870fb126f66285808c7e094afcfc4eda8a970f6648cdf0dbd6de", Ok(())),
+        ]);
+        for address in ["Alice.near", "a", "alice..near", ""] {
+            assert!(AddressFormat::Near.validate(address).is_err(), "{}", address);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::str::FromStr;
+
+use near_sdk::near;
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::ChainId;
+
+/// In JSON either the struct or its CAIP-19 string
+/// (`eip155:1/erc20:0xA0b8...`) is accepted; it is always written as the
+/// struct.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CanonicalAssetId {
+    pub source_chain_id: ChainId,
+    pub source_contract: String,
+    pub asset_standard: AssetStandard,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetStandard {
+    Native,
+    ERC20,
+    ERC721,
+    ERC1155,
+    SPL,
+    Custom(String),
+}
+
+impl AssetStandard {
+    /// CAIP-19 asset namespace, e.g. `erc20`.
+    pub fn namespace(&self) -> String {
+        match self {
+            Self::Native => "native".to_string(),
+            Self::ERC20 => "erc20".to_string(),
+            Self::ERC721 => "erc721".to_string(),
+            Self::ERC1155 => "erc1155".to_string(),
+            Self::SPL => "spl".to_string(),
+            Self::Custom(namespace) => namespace.to_ascii_lowercase(),
+        }
+    }
+
+    pub fn from_namespace(namespace: &str) -> Self {
+        match namespace {
+            "native" => Self::Native,
+            "erc20" => Self::ERC20,
+            "erc721" => Self::ERC721,
+            "erc1155" => Self::ERC1155,
+            "spl" => Self::SPL,
+            other => Self::Custom(other.to_string()),
+        }
+    }
+}
+
+impl CanonicalAssetId {
+    /// CAIP-19 asset ID, `<chain_id>/<asset namespace>:<contract>`. The
+    /// contract keeps its case; characters outside the CAIP-19 reference
+    /// charset (e.g. `_` in NEAR accounts) are percent-encoded.
+    pub fn to_caip19(&self) -> String {
+        format!(
+            "{}/{}:{}",
+            self.source_chain_id,
+            self.asset_standard.namespace(),
+            encode_reference(&self.source_contract)
+        )
+    }
+
+    /// Checks that the asset can be written as CAIP-19 and parsed back to
+    /// itself: a non-empty contract of at most 128 characters once encoded,
+    /// and a `Custom` standard that is a valid namespace of its own.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.source_contract.is_empty() {
+            return Err("Asset contract cannot be empty".to_string());
+        }
+        if encode_reference(&self.source_contract).len() > 128 {
+            return Err("Asset contract too long".to_string());
+        }
+        if let AssetStandard::Custom(namespace) = &self.asset_standard {
+            if !namespace_ok(namespace) || AssetStandard::from_namespace(namespace) != self.asset_standard {
+                return Err(format!("Invalid asset standard {}", namespace));
+            }
+        }
+        Ok(())
+    }
+
+    /// Stable identity of the asset, `<chain_id>/<asset namespace>:<contract>`.
+    /// It is shaped like CAIP-19 but is not the CAIP-19 string: the contract is
+    /// not percent-encoded, so characters such as `_` stay as they are, and
+    /// hex addresses are lowercased so differently checksummed spellings map to
+    /// the same key. `to_caip19` gives the CAIP-19 form.
+    pub fn key(&self) -> String {
+        format!(
+            "{}/{}:{}",
+            self.source_chain_id,
+            self.asset_standard.namespace(),
+            normalize_contract(&self.source_contract)
+        )
+    }
+}
+
+/// Lowercases `0x`-prefixed hex addresses; other contract identifiers are
+/// case-sensitive and kept as is.
+pub fn normalize_contract(contract: &str) -> String {
+    match contract.strip_prefix("0x").or_else(|| contract.strip_prefix("0X")) {
+        Some(hex) if !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            format!("0x{}", hex.to_ascii_lowercase())
+        }
+        _ => contract.to_string(),
+    }
+}
+
+impl FromStr for CanonicalAssetId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (chain_id, asset) = s
+            .split_once('/')
+            .ok_or_else(|| format!("Invalid CAIP-19 asset {}: expected chain_id/namespace:reference", s))?;
+        let (namespace, reference) = asset
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid CAIP-19 asset {}: expected chain_id/namespace:reference", s))?;
+        let reference_ok = (1..=128).contains(&reference.len())
+            && reference.bytes().all(|b| is_reference_char(b) || b == b'%');
+        if !namespace_ok(namespace) || !reference_ok {
+            return Err(format!("Invalid CAIP-19 asset {}", s));
+        }
+        Ok(Self {
+            source_chain_id: chain_id.parse()?,
+            source_contract: decode_reference(reference).ok_or_else(|| format!("Invalid CAIP-19 asset {}", s))?,
+            asset_standard: AssetStandard::from_namespace(namespace),
+        })
+    }
+}
+
+fn namespace_ok(namespace: &str) -> bool {
+    (3..=8).contains(&namespace.len())
+        && namespace.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn is_reference_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'.'
+}
+
+fn encode_reference(contract: &str) -> String {
+    contract
+        .bytes()
+        .map(|b| if is_reference_char(b) { (b as char).to_string() } else { format!("%{:02X}", b) })
+        .collect()
+}
+
+// Undoes `encode_reference`; `None` on a malformed escape or invalid UTF-8.
+fn decode_reference(reference: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(reference.len());
+    let mut rest = reference.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+enum CanonicalAssetIdRepr {
+    Caip19(String),
+    Struct {
+        source_chain_id: ChainId,
+        source_contract: String,
+        asset_standard: AssetStandard,
+    },
+}
+
+impl<'de> Deserialize<'de> for CanonicalAssetId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match CanonicalAssetIdRepr::deserialize(deserializer)? {
+            CanonicalAssetIdRepr::Caip19(s) => s.parse().map_err(de::Error::custom),
+            CanonicalAssetIdRepr::Struct {
+                source_chain_id,
+                source_contract,
+                asset_standard,
+            } => Ok(Self {
+                source_chain_id,
+                source_contract,
+                asset_standard,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(chain: &str, contract: &str, asset_standard: AssetStandard) -> CanonicalAssetId {
+        CanonicalAssetId {
+            source_chain_id: chain.parse().unwrap(),
+            source_contract: contract.to_string(),
+            asset_standard,
+        }
+    }
+
+    #[test]
+    fn caip19_round_trip() {
+        let cases = [
+            (
+                asset("eip155:1", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", AssetStandard::ERC20),
+                "eip155:1/erc20:0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            ),
+            (
+                asset("solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", AssetStandard::SPL),
+                "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp/spl:EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            ),
+            (asset("near:mainnet", "wrap.near", AssetStandard::Native), "near:mainnet/native:wrap.near"),
+            (asset("near:mainnet", "token_v2.near", AssetStandard::ERC20), "near:mainnet/erc20:token%5Fv2.near"),
+            (asset("eip155:1", "100%", AssetStandard::ERC721), "eip155:1/erc721:100%25"),
+            (asset("eip155:1", "1", AssetStandard::ERC1155), "eip155:1/erc1155:1"),
+            (asset("cosmos:cosmoshub-4", "uatom", AssetStandard::Custom("slip44".to_string())), "cosmos:cosmoshub-4/slip44:uatom"),
+        ];
+        for (asset, caip19) in cases {
+            assert_eq!(asset.validate(), Ok(()), "{}", caip19);
+            assert_eq!(asset.to_caip19(), caip19);
+            assert_eq!(caip19.parse::<CanonicalAssetId>(), Ok(asset), "{}", caip19);
+        }
+    }
+
+    #[test]
+    fn caip19_parse_errors() {
+        for caip19 in [
+            "eip155:1",
+            "eip155:1/erc20",
+            "eip155:1/erc20:",
+            "eip155:1/ERC20:0xa0b8",
+            "eip155:1/er:0xa0b8",
+            "eip155:1/erc20:token_v2.near",
+            "eip155:1/erc20:token%5",
+            "eip155:1/erc20:token%zz",
+            "eip155:1/erc20:%FF",
+            "ethereum/erc20:0xa0b8",
+        ] {
+            assert!(caip19.parse::<CanonicalAssetId>().is_err(), "{}", caip19);
+        }
+        let too_long = format!("eip155:1/erc20:{}", "a".repeat(129));
+        assert!(too_long.parse::<CanonicalAssetId>().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_assets_without_caip19_form() {
+        let cases = [
+            (asset("eip155:1", "", AssetStandard::Native), "Asset contract cannot be empty"),
+            (asset("near:mainnet", &"_".repeat(43), AssetStandard::ERC20), "Asset contract too long"),
+            (asset("eip155:1", "0xa0b8", AssetStandard::Custom("ERC20".to_string())), "Invalid asset standard ERC20"),
+            (asset("eip155:1", "0xa0b8", AssetStandard::Custom("erc20".to_string())), "Invalid asset standard erc20"),
+            (asset("eip155:1", "0xa0b8", AssetStandard::Custom("x".to_string())), "Invalid asset standard x"),
+        ];
+        for (asset, error) in cases {
+            assert_eq!(asset.validate(), Err(error.to_string()));
+        }
+    }
+
+    #[test]
+    fn key_normalizes_hex_contracts() {
+        let checksummed = asset("eip155:1", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", AssetStandard::ERC20);
+        let lowercase = asset("eip155:1", "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", AssetStandard::ERC20);
+        assert_eq!(checksummed.key(), lowercase.key());
+        assert_eq!(checksummed.key(), "eip155:1/erc20:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+    }
+}
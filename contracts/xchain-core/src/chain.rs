@@ -0,0 +1,92 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use near_sdk::near;
+
+/// A CAIP-2 blockchain ID such as `eip155:1`, `near:mainnet` or
+/// `solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp`. Parsing enforces the CAIP-2
+/// grammar, so two IDs are the same chain only if they are equal.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct ChainId(String);
+
+impl ChainId {
+    pub fn namespace(&self) -> &str {
+        self.0.split_once(':').map(|(namespace, _)| namespace).unwrap_or_default()
+    }
+
+    pub fn reference(&self) -> &str {
+        self.0.split_once(':').map(|(_, reference)| reference).unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for ChainId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (namespace, reference) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid chain id {}: expected namespace:reference", s))?;
+        let namespace_ok = (3..=8).contains(&namespace.len())
+            && namespace.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+        let reference_ok = (1..=32).contains(&reference.len())
+            && reference.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !namespace_ok || !reference_ok {
+            return Err(format!("Invalid chain id {}", s));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl fmt::Display for ChainId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl BorshDeserialize for ChainId {
+    fn deserialize_reader<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let s = String::deserialize_reader(reader)?;
+        s.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Serialize for ChainId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChainId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String as Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub enum AddressFormat {
+    Evm,
+    Solana,
+    Bitcoin,
+    Near,
+    Cosmos { prefix: String },
+}
+
+/// Per-chain settings of a bridge. `finality_blocks` is the number of
+/// confirmations relayers and validators wait for before attesting.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainConfig {
+    pub enabled: bool,
+    pub address_format: AddressFormat,
+    pub finality_blocks: u64,
+}
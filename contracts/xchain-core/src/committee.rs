@@ -0,0 +1,675 @@
+use std::str::FromStr;
+
+use near_sdk::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use near_sdk::store::LookupMap;
+use near_sdk::{env, near, require, AccountId, CurveType, IntoStorageKey, PublicKey, Timestamp};
+
+use crate::events::NepXChainEvent;
+use crate::CommitteeProof;
+
+pub const DEFAULT_EPOCH_GRACE_PERIOD: u64 = 24 * 60 * 60 * 1_000_000_000;
+pub const DEFAULT_MAX_VALIDATORS: u32 = 100;
+
+const COMMITTEE_DOMAIN: &[u8] = b"nep_xchain_committee";
+const HANDOVER_DOMAIN: &[u8] = b"nep_xchain_handover";
+const ETH_SIGNED_MESSAGE_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n32";
+
+/// A committee member: a NEAR ed25519/secp256k1 public key, or a 20-byte
+/// Ethereum address for operators signing with EVM tooling. In JSON it is the
+/// NEAR key string (`ed25519:...`) or a `0x`-prefixed hex address.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ValidatorKey {
+    Near(PublicKey),
+    Eth([u8; 20]),
+}
+
+impl From<PublicKey> for ValidatorKey {
+    fn from(public_key: PublicKey) -> Self {
+        Self::Near(public_key)
+    }
+}
+
+impl FromStr for ValidatorKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(address) = s.strip_prefix("0x") {
+            let bytes = hex::decode(address).map_err(|e| format!("Invalid Ethereum address: {}", e))?;
+            let address = <[u8; 20]>::try_from(bytes).map_err(|_| "Ethereum address must be 20 bytes".to_string())?;
+            return Ok(Self::Eth(address));
+        }
+        PublicKey::from_str(s)
+            .map(Self::Near)
+            .map_err(|e| format!("Invalid public key: {}", e))
+    }
+}
+
+impl Serialize for ValidatorKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Near(public_key) => public_key.serialize(serializer),
+            Self::Eth(address) => serializer.serialize_str(&format!("0x{}", hex::encode(address))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ValidatorKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatorConfig {
+    pub public_key: ValidatorKey,
+    pub weight: u64,
+    pub operator_id: Option<AccountId>,
+    pub name: Option<String>,
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatorInfo {
+    pub public_key: ValidatorKey,
+    pub weight: u64,
+    pub operator_id: Option<AccountId>,
+    pub name: Option<String>,
+    pub added_at: Timestamp,
+}
+
+impl From<&ValidatorInfo> for ValidatorConfig {
+    fn from(info: &ValidatorInfo) -> Self {
+        Self {
+            public_key: info.public_key.clone(),
+            weight: info.weight,
+            operator_id: info.operator_id.clone(),
+            name: info.name.clone(),
+        }
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatorEpoch {
+    pub epoch: u64,
+    pub validators: Vec<ValidatorInfo>,
+    pub quorum: u64,
+    pub activated_at: Timestamp,
+    pub expires_at: Option<Timestamp>,
+}
+
+impl ValidatorEpoch {
+    pub fn total_weight(&self) -> u64 {
+        total_weight(self.validators.iter().map(|v| v.weight))
+    }
+
+    pub fn get(&self, public_key: &ValidatorKey) -> Option<&ValidatorInfo> {
+        self.validators.iter().find(|v| &v.public_key == public_key)
+    }
+}
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct ValidatorHandover {
+    pub new_epoch: u64,
+    pub validators: Vec<ValidatorConfig>,
+    pub quorum: u64,
+}
+
+impl ValidatorHandover {
+    pub fn signing_message(&self) -> Vec<u8> {
+        let mut message = HANDOVER_DOMAIN.to_vec();
+        message.extend(near_sdk::borsh::to_vec(self).expect("Failed to serialize handover"));
+        message
+    }
+}
+
+impl CommitteeProof {
+    /// The digest validators of `epoch` sign for `message`. It is bound to the
+    /// verifying contract so a proof cannot be replayed on another deployment.
+    pub fn digest(epoch: u64, message: &[u8]) -> Vec<u8> {
+        let mut data = COMMITTEE_DOMAIN.to_vec();
+        data.extend(near_sdk::borsh::to_vec(&env::current_account_id()).expect("Failed to serialize account id"));
+        data.extend_from_slice(&epoch.to_le_bytes());
+        data.extend_from_slice(message);
+        env::sha256(&data)
+    }
+}
+
+#[near(serializers = [borsh])]
+pub struct Committee {
+    epochs: LookupMap<u64, ValidatorEpoch>,
+    current_epoch: u64,
+    grace_period: u64,
+    max_validators: u32,
+}
+
+impl Committee {
+    /// Starts epoch 0 with `validators` at weight 1 and the default quorum. An
+    /// empty set is allowed: its quorum is 1 and its total weight 0, so it
+    /// accepts no proof until the owner adds a validator.
+    pub fn new<S: IntoStorageKey>(prefix: S, validators: Vec<ValidatorKey>) -> Self {
+        let validators = dedup(
+            validators
+                .into_iter()
+                .map(|public_key| ValidatorConfig {
+                    public_key,
+                    weight: 1,
+                    operator_id: None,
+                    name: None,
+                })
+                .collect(),
+        );
+        require!(validators.len() <= DEFAULT_MAX_VALIDATORS as usize, "Too many validators");
+        let now = env::block_timestamp();
+        let validators: Vec<ValidatorInfo> = validators
+            .into_iter()
+            .map(|config| into_info(config, now))
+            .collect();
+        let quorum = Self::default_quorum(total_weight(validators.iter().map(|v| v.weight)));
+
+        let mut epochs = LookupMap::new(prefix);
+        epochs.insert(
+            0,
+            ValidatorEpoch {
+                epoch: 0,
+                validators,
+                quorum,
+                activated_at: now,
+                expires_at: None,
+            },
+        );
+        Self {
+            epochs,
+            current_epoch: 0,
+            grace_period: DEFAULT_EPOCH_GRACE_PERIOD,
+            max_validators: DEFAULT_MAX_VALIDATORS,
+        }
+    }
+
+    pub fn current_epoch(&self) -> u64 {
+        self.current_epoch
+    }
+
+    pub fn current(&self) -> &ValidatorEpoch {
+        self.epochs
+            .get(&self.current_epoch)
+            .unwrap_or_else(|| env::panic_str("Missing current validator epoch"))
+    }
+
+    pub fn get_epoch(&self, epoch: u64) -> Option<&ValidatorEpoch> {
+        self.epochs.get(&epoch)
+    }
+
+    pub fn validators(&self) -> &[ValidatorInfo] {
+        &self.current().validators
+    }
+
+    pub fn is_validator(&self, public_key: &ValidatorKey) -> bool {
+        self.current().get(public_key).is_some()
+    }
+
+    /// 2/3+1 of `total_weight`, the quorum of the initial set.
+    pub fn default_quorum(total_weight: u64) -> u64 {
+        total_weight / 3 * 2 + (total_weight % 3) * 2 / 3 + 1
+    }
+
+    pub fn grace_period(&self) -> u64 {
+        self.grace_period
+    }
+
+    pub fn set_grace_period(&mut self, grace_period: u64) {
+        self.grace_period = grace_period;
+    }
+
+    pub fn max_validators(&self) -> u32 {
+        self.max_validators
+    }
+
+    /// Changes the maximum set size and returns the previous one. It cannot
+    /// drop below the size of the current set.
+    pub fn set_max_validators(&mut self, max_validators: u32) -> u32 {
+        require!(
+            max_validators as usize >= self.validators().len().max(1),
+            "Limit below current validator count"
+        );
+        std::mem::replace(&mut self.max_validators, max_validators)
+    }
+
+    /// Panics unless validators of `proof.epoch` holding at least the epoch's
+    /// quorum weight signed `message`, and that epoch has not expired.
+    pub fn verify(&self, proof: &CommitteeProof, message: &[u8]) {
+        let set = self
+            .epochs
+            .get(&proof.epoch)
+            .unwrap_or_else(|| env::panic_str("Unknown validator epoch"));
+        if let Some(expires_at) = set.expires_at {
+            require!(env::block_timestamp() < expires_at, "Validator epoch expired");
+        }
+
+        let digest = CommitteeProof::digest(proof.epoch, message);
+        let mut signers: Vec<&ValidatorKey> = Vec::new();
+        let mut signed_weight: u64 = 0;
+        for (public_key, signature) in &proof.signatures {
+            let Some(validator) = set.get(public_key) else {
+                continue;
+            };
+            if signers.contains(&public_key) {
+                continue;
+            }
+            require!(
+                verify_signature(public_key, signature, &digest),
+                "Invalid validator signature"
+            );
+            signers.push(public_key);
+            signed_weight = signed_weight.saturating_add(validator.weight);
+        }
+        require!(signed_weight >= set.quorum, "Insufficient validator signatures");
+    }
+
+    /// Whether `public_key` belonged to `epoch` and produced `signature` over
+    /// `message` for that epoch.
+    pub fn is_signed_by(&self, epoch: u64, public_key: &ValidatorKey, signature: &[u8], message: &[u8]) -> bool {
+        self.epochs.get(&epoch).is_some_and(|set| set.get(public_key).is_some())
+            && verify_signature(public_key, signature, &CommitteeProof::digest(epoch, message))
+    }
+
+    /// Activates `validators` as the next epoch, authorized by a quorum of the
+    /// current epoch signing the `ValidatorHandover`. The outgoing epoch stays
+    /// valid for the grace period.
+    pub fn rotate(&mut self, validators: Vec<ValidatorConfig>, quorum: u64, proof: &CommitteeProof) -> u64 {
+        require!(
+            proof.epoch == self.current_epoch,
+            "Handover must be signed by the current epoch"
+        );
+        let handover = ValidatorHandover {
+            new_epoch: self.current_epoch + 1,
+            validators,
+            quorum,
+        };
+        self.verify(proof, &handover.signing_message());
+        let expires_at = env::block_timestamp() + self.grace_period;
+        self.activate(handover.validators, handover.quorum, expires_at)
+    }
+
+    pub fn add_validator(&mut self, config: ValidatorConfig) -> u64 {
+        require!(!self.is_validator(&config.public_key), "Validator already exists");
+        let mut validators: Vec<ValidatorConfig> = self.validators().iter().map(Into::into).collect();
+        validators.push(config);
+        let expires_at = env::block_timestamp() + self.grace_period;
+        self.activate(validators, self.current().quorum, expires_at)
+    }
+
+    /// Removes `public_key` in a new epoch. The outgoing epoch expires immediately
+    /// so the removed key cannot attest anymore. The last validator cannot be
+    /// removed. The quorum is kept, capped at the remaining total weight.
+    pub fn remove_validator(&mut self, public_key: &ValidatorKey) -> u64 {
+        require!(self.is_validator(public_key), "Validator not found");
+        let validators: Vec<ValidatorConfig> = self
+            .validators()
+            .iter()
+            .filter(|v| &v.public_key != public_key)
+            .map(Into::into)
+            .collect();
+        let quorum = self.current().quorum.min(total_weight(validators.iter().map(|v| v.weight)));
+        self.activate(validators, quorum, env::block_timestamp())
+    }
+
+    /// Changes the quorum weight of the current epoch and returns the previous one.
+    pub fn set_quorum(&mut self, quorum: u64) -> u64 {
+        let current_epoch = self.current_epoch;
+        let set = self
+            .epochs
+            .get_mut(&current_epoch)
+            .unwrap_or_else(|| env::panic_str("Missing current validator epoch"));
+        require!(quorum > 0, "Quorum must be positive");
+        require!(quorum <= set.total_weight(), "Quorum exceeds total weight");
+        std::mem::replace(&mut set.quorum, quorum)
+    }
+
+    /// Starts a new epoch with `validators` and `quorum`; the outgoing epoch
+    /// expires at `previous_expires_at`.
+    pub fn activate(
+        &mut self,
+        validators: Vec<ValidatorConfig>,
+        quorum: u64,
+        previous_expires_at: Timestamp,
+    ) -> u64 {
+        let validators = dedup(validators);
+        require!(!validators.is_empty(), "Validator set cannot be empty");
+        require!(validators.len() <= self.max_validators as usize, "Too many validators");
+        require!(validators.iter().all(|v| v.weight > 0), "Validator weight must be positive");
+
+        let now = env::block_timestamp();
+        let old_epoch = self.current_epoch;
+        let new_epoch = old_epoch + 1;
+        let previous = self.current().clone();
+        let validators: Vec<ValidatorInfo> = validators
+            .into_iter()
+            .map(|config| {
+                let added_at = previous.get(&config.public_key).map_or(now, |v| v.added_at);
+                into_info(config, added_at)
+            })
+            .collect();
+        let total = total_weight(validators.iter().map(|v| v.weight));
+        require!(quorum > 0, "Quorum must be positive");
+        require!(quorum <= total, "Quorum exceeds total weight");
+
+        if let Some(previous) = self.epochs.get_mut(&old_epoch) {
+            previous.expires_at = Some(previous_expires_at);
+        }
+        self.epochs.insert(
+            new_epoch,
+            ValidatorEpoch {
+                epoch: new_epoch,
+                validators: validators.clone(),
+                quorum,
+                activated_at: now,
+                expires_at: None,
+            },
+        );
+        self.current_epoch = new_epoch;
+
+        NepXChainEvent::ValidatorSetRotated {
+            caller: env::predecessor_account_id(),
+            old_epoch,
+            new_epoch,
+            validators: validators.into_iter().map(|v| v.public_key).collect(),
+            quorum,
+        }
+        .emit();
+        new_epoch
+    }
+}
+
+fn into_info(config: ValidatorConfig, added_at: Timestamp) -> ValidatorInfo {
+    ValidatorInfo {
+        public_key: config.public_key,
+        weight: config.weight,
+        operator_id: config.operator_id,
+        name: config.name,
+        added_at,
+    }
+}
+
+fn total_weight(weights: impl Iterator<Item = u64>) -> u64 {
+    weights.fold(0u64, |total, weight| {
+        total
+            .checked_add(weight)
+            .unwrap_or_else(|| env::panic_str("Total validator weight overflow"))
+    })
+}
+
+fn dedup(validators: Vec<ValidatorConfig>) -> Vec<ValidatorConfig> {
+    let mut unique: Vec<ValidatorConfig> = Vec::with_capacity(validators.len());
+    for validator in validators {
+        if !unique.iter().any(|v| v.public_key == validator.public_key) {
+            unique.push(validator);
+        }
+    }
+    unique
+}
+
+/// Checks `signature` by `key` over a 32-byte `message`: plain ed25519, or
+/// EIP-191 `eth_sign` for secp256k1 keys and Ethereum addresses.
+pub fn verify_signature(key: &ValidatorKey, signature: &[u8], message: &[u8]) -> bool {
+    match key {
+        ValidatorKey::Near(public_key) if matches!(public_key.curve_type(), CurveType::ED25519) => {
+            let (Ok(signature), Ok(key)) = (
+                <[u8; 64]>::try_from(signature),
+                <[u8; 32]>::try_from(&public_key.as_bytes()[1..]),
+            ) else {
+                return false;
+            };
+            env::ed25519_verify(&signature, message, &key)
+        }
+        ValidatorKey::Near(public_key) => {
+            ecrecover_eth_signed(signature, message).is_some_and(|recovered| recovered[..] == public_key.as_bytes()[1..])
+        }
+        ValidatorKey::Eth(address) => {
+            ecrecover_eth_signed(signature, message).is_some_and(|recovered| eth_address(&recovered) == *address)
+        }
+    }
+}
+
+/// Recovers the uncompressed secp256k1 key behind a 65-byte `r || s || v`
+/// signature over the EIP-191 (`eth_sign`) hash of `message`.
+fn ecrecover_eth_signed(signature: &[u8], message: &[u8]) -> Option<[u8; 64]> {
+    if signature.len() != 65 {
+        return None;
+    }
+    let v = match signature[64] {
+        v @ (27 | 28) => v - 27,
+        v @ (0 | 1) => v,
+        _ => return None,
+    };
+    let hash = env::keccak256_array([ETH_SIGNED_MESSAGE_PREFIX, message].concat());
+    env::ecrecover(&hash, &signature[..64], v, true)
+}
+
+fn eth_address(public_key: &[u8; 64]) -> [u8; 20] {
+    let hash = env::keccak256_array(public_key);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey as EthSigningKey;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn setup() {
+        setup_at(0);
+    }
+
+    fn setup_at(timestamp: Timestamp) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("bridge.near".parse().unwrap())
+            .block_timestamp(timestamp)
+            .build());
+    }
+
+    fn ed25519_key(seed: u8) -> (ed25519_dalek::SigningKey, ValidatorKey) {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[seed; 32]);
+        let public_key = PublicKey::from_parts(CurveType::ED25519, signing_key.verifying_key().to_bytes().to_vec());
+        (signing_key, ValidatorKey::Near(public_key.unwrap()))
+    }
+
+    fn secp256k1_signer(seed: u8) -> EthSigningKey {
+        EthSigningKey::from_bytes(&[seed; 32].into()).unwrap()
+    }
+
+    fn secp256k1_key(signing_key: &EthSigningKey) -> ValidatorKey {
+        let point = signing_key.verifying_key().to_encoded_point(false);
+        ValidatorKey::Near(PublicKey::from_parts(CurveType::SECP256K1, point.as_bytes()[1..].to_vec()).unwrap())
+    }
+
+    fn eth_key(signing_key: &EthSigningKey) -> ValidatorKey {
+        let point = signing_key.verifying_key().to_encoded_point(false);
+        ValidatorKey::Eth(eth_address(point.as_bytes()[1..].try_into().unwrap()))
+    }
+
+    /// `r || s || v` over `hash`, with `v` as 27/28.
+    fn sign_hash(signing_key: &EthSigningKey, hash: &[u8]) -> Vec<u8> {
+        let (signature, recovery_id) = signing_key.sign_prehash_recoverable(hash).unwrap();
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        bytes
+    }
+
+    fn eth_sign(signing_key: &EthSigningKey, message: &[u8]) -> Vec<u8> {
+        sign_hash(signing_key, &env::keccak256([ETH_SIGNED_MESSAGE_PREFIX, message].concat()))
+    }
+
+    #[test]
+    fn secp256k1_signatures_use_eip191_prefix() {
+        setup();
+        let signer = secp256k1_signer(1);
+        let message = env::sha256(b"payload");
+
+        let signature = eth_sign(&signer, &message);
+        assert!(verify_signature(&secp256k1_key(&signer), &signature, &message));
+        assert!(verify_signature(&eth_key(&signer), &signature, &message));
+
+        let mut raw_v = signature.clone();
+        raw_v[64] -= 27;
+        assert!(verify_signature(&eth_key(&signer), &raw_v, &message));
+
+        let unprefixed = sign_hash(&signer, &env::keccak256(&message));
+        assert!(!verify_signature(&secp256k1_key(&signer), &unprefixed, &message));
+        assert!(!verify_signature(&eth_key(&signer), &unprefixed, &message));
+    }
+
+    #[test]
+    fn secp256k1_wrong_recovery_id_rejected() {
+        setup();
+        let signer = secp256k1_signer(1);
+        let message = env::sha256(b"payload");
+        let mut signature = eth_sign(&signer, &message);
+
+        signature[64] ^= 1;
+        assert!(!verify_signature(&secp256k1_key(&signer), &signature, &message));
+        assert!(!verify_signature(&eth_key(&signer), &signature, &message));
+
+        signature[64] = 29;
+        assert!(!verify_signature(&eth_key(&signer), &signature, &message));
+        assert!(!verify_signature(&eth_key(&signer), &signature[..64], &message));
+    }
+
+    #[test]
+    fn secp256k1_wrong_signer_rejected() {
+        setup();
+        let signer = secp256k1_signer(1);
+        let other = secp256k1_signer(2);
+        let message = env::sha256(b"payload");
+        let signature = eth_sign(&other, &message);
+
+        assert!(!verify_signature(&secp256k1_key(&signer), &signature, &message));
+        assert!(!verify_signature(&eth_key(&signer), &signature, &message));
+    }
+
+    type Signer = (ValidatorKey, Box<dyn Fn(&[u8]) -> Vec<u8>>);
+
+    fn mixed_committee() -> (Committee, Vec<Signer>) {
+        let (ed_signer, ed_key) = ed25519_key(3);
+        let secp_signer = secp256k1_signer(4);
+        let eth_signer = secp256k1_signer(5);
+        let signers: Vec<Signer> = vec![
+            (ed_key, Box::new(move |digest| {
+                use ed25519_dalek::Signer;
+                ed_signer.sign(digest).to_bytes().to_vec()
+            })),
+            (secp256k1_key(&secp_signer), Box::new(move |digest| eth_sign(&secp_signer, digest))),
+            (eth_key(&eth_signer), Box::new(move |digest| eth_sign(&eth_signer, digest))),
+        ];
+        let committee = Committee::new(b"c", signers.iter().map(|(key, _)| key.clone()).collect());
+        (committee, signers)
+    }
+
+    #[test]
+    fn mixed_quorum_verifies() {
+        setup();
+        let (committee, signers) = mixed_committee();
+        assert_eq!(committee.current().quorum, 3);
+        let digest = CommitteeProof::digest(0, b"message");
+        let proof = CommitteeProof {
+            epoch: 0,
+            signatures: signers.iter().map(|(key, sign)| (key.clone(), sign(&digest))).collect(),
+        };
+        committee.verify(&proof, b"message");
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient validator signatures")]
+    fn mixed_quorum_requires_all_curves() {
+        setup();
+        let (committee, signers) = mixed_committee();
+        let digest = CommitteeProof::digest(0, b"message");
+        let proof = CommitteeProof {
+            epoch: 0,
+            signatures: signers.iter().skip(1).map(|(key, sign)| (key.clone(), sign(&digest))).collect(),
+        };
+        committee.verify(&proof, b"message");
+    }
+
+    fn ed25519_proof(epoch: u64, signers: &[&ed25519_dalek::SigningKey], message: &[u8]) -> CommitteeProof {
+        use ed25519_dalek::Signer;
+        let digest = CommitteeProof::digest(epoch, message);
+        CommitteeProof {
+            epoch,
+            signatures: signers
+                .iter()
+                .map(|signer| {
+                    let public_key = PublicKey::from_parts(CurveType::ED25519, signer.verifying_key().to_bytes().to_vec());
+                    (ValidatorKey::Near(public_key.unwrap()), signer.sign(&digest).to_bytes().to_vec())
+                })
+                .collect(),
+        }
+    }
+
+    fn validator_config(public_key: ValidatorKey) -> ValidatorConfig {
+        ValidatorConfig { public_key, weight: 1, operator_id: None, name: None }
+    }
+
+    const ROTATED_AT: Timestamp = 1_000;
+
+    /// Epoch 0 of keys 1 and 2, then key 3 added at `ROTATED_AT` as epoch 1.
+    fn rotated_committee() -> (Committee, Vec<ed25519_dalek::SigningKey>) {
+        let keys: Vec<_> = (1..=3).map(ed25519_key).collect();
+        setup_at(0);
+        let mut committee = Committee::new(b"c", keys[..2].iter().map(|(_, key)| key.clone()).collect());
+        setup_at(ROTATED_AT);
+        committee.add_validator(validator_config(keys[2].1.clone()));
+        (committee, keys.into_iter().map(|(signer, _)| signer).collect())
+    }
+
+    #[test]
+    fn previous_epoch_accepted_during_grace_period() {
+        let (committee, signers) = rotated_committee();
+        setup_at(ROTATED_AT + DEFAULT_EPOCH_GRACE_PERIOD - 1);
+        committee.verify(&ed25519_proof(0, &[&signers[0], &signers[1]], b"message"), b"message");
+        committee.verify(&ed25519_proof(1, &[&signers[1], &signers[2]], b"message"), b"message");
+    }
+
+    #[test]
+    #[should_panic(expected = "Validator epoch expired")]
+    fn previous_epoch_rejected_after_grace_period() {
+        let (committee, signers) = rotated_committee();
+        setup_at(ROTATED_AT + DEFAULT_EPOCH_GRACE_PERIOD);
+        committee.verify(&ed25519_proof(0, &[&signers[0], &signers[1]], b"message"), b"message");
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient validator signatures")]
+    fn retired_validator_signatures_ignored() {
+        let (mut committee, signers) = rotated_committee();
+        let (_, retired) = ed25519_key(1);
+        committee.remove_validator(&retired);
+        assert_eq!(committee.current_epoch(), 2);
+        committee.verify(&ed25519_proof(2, &[&signers[0], &signers[1]], b"message"), b"message");
+    }
+
+    #[test]
+    #[should_panic(expected = "Validator epoch expired")]
+    fn epoch_of_removed_validator_expires_immediately() {
+        let (mut committee, signers) = rotated_committee();
+        let (_, retired) = ed25519_key(1);
+        committee.remove_validator(&retired);
+        committee.verify(&ed25519_proof(1, &[&signers[0], &signers[1]], b"message"), b"message");
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient validator signatures")]
+    fn empty_committee_accepts_no_proof() {
+        setup();
+        let committee = Committee::new(b"c", Vec::new());
+        assert_eq!(committee.current().quorum, 1);
+        committee.verify(&ed25519_proof(0, &[&ed25519_key(1).0], b"message"), b"message");
+    }
+}
@@ -0,0 +1,261 @@
+use near_sdk::{env, near, require};
+
+const HEADER_TRANSACTIONS_ROOT_INDEX: usize = 4;
+const HEADER_RECEIPTS_ROOT_INDEX: usize = 5;
+const HEADER_NUMBER_INDEX: usize = 8;
+
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct EthReceiptProof {
+    pub header_rlp: Vec<u8>,
+    pub receipt_index: u64,
+    pub receipt_proof: Vec<Vec<u8>>,
+    pub log_index: u64,
+    /// Proof of transaction `receipt_index` in the header's transactions trie,
+    /// which ties the log to its transaction hash.
+    pub transaction_proof: Vec<Vec<u8>>,
+}
+
+/// The trie roots a verified block header commits to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EthHeaderRoots {
+    pub transactions_root: [u8; 32],
+    pub receipts_root: [u8; 32],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EthLog {
+    pub address: [u8; 20],
+    pub topics: Vec<[u8; 32]>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RlpItem<'a> {
+    pub raw: &'a [u8],
+    pub payload: &'a [u8],
+    pub is_list: bool,
+}
+
+impl<'a> RlpItem<'a> {
+    pub fn decode(data: &'a [u8]) -> Option<Self> {
+        let (item, consumed) = decode_prefix(data)?;
+        (consumed == data.len()).then_some(item)
+    }
+
+    pub fn list(&self) -> Option<Vec<RlpItem<'a>>> {
+        if !self.is_list {
+            return None;
+        }
+        let mut items = Vec::new();
+        let mut rest = self.payload;
+        while !rest.is_empty() {
+            let (item, consumed) = decode_prefix(rest)?;
+            items.push(item);
+            rest = &rest[consumed..];
+        }
+        Some(items)
+    }
+
+    pub fn bytes(&self) -> Option<&'a [u8]> {
+        (!self.is_list).then_some(self.payload)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        let bytes = self.bytes()?;
+        if bytes.len() > 8 {
+            return None;
+        }
+        Some(bytes.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b)))
+    }
+}
+
+fn decode_prefix(data: &[u8]) -> Option<(RlpItem<'_>, usize)> {
+    let prefix = *data.first()?;
+    let (is_list, offset, length) = match prefix {
+        0x00..=0x7f => (false, 0, 1),
+        0x80..=0xb7 => (false, 1, usize::from(prefix - 0x80)),
+        0xb8..=0xbf => {
+            let len_of_len = usize::from(prefix - 0xb7);
+            (false, 1 + len_of_len, read_length(data.get(1..1 + len_of_len)?)?)
+        }
+        0xc0..=0xf7 => (true, 1, usize::from(prefix - 0xc0)),
+        0xf8..=0xff => {
+            let len_of_len = usize::from(prefix - 0xf7);
+            (true, 1 + len_of_len, read_length(data.get(1..1 + len_of_len)?)?)
+        }
+    };
+    let end = offset.checked_add(length)?;
+    let raw = data.get(..end)?;
+    let payload = if prefix <= 0x7f { raw } else { &raw[offset..] };
+    Some((RlpItem { raw, payload, is_list }, end))
+}
+
+fn read_length(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() > std::mem::size_of::<usize>() {
+        return None;
+    }
+    Some(bytes.iter().fold(0usize, |acc, b| (acc << 8) | usize::from(*b)))
+}
+
+pub fn rlp_encode_u64(value: u64) -> Vec<u8> {
+    if value == 0 {
+        return vec![0x80];
+    }
+    if value < 0x80 {
+        return vec![value as u8];
+    }
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count();
+    let mut encoded = vec![0x80 + (8 - skip) as u8];
+    encoded.extend_from_slice(&bytes[skip..]);
+    encoded
+}
+
+/// Checks `header_rlp` hashes to `block_hash` and is block `block_number`, and
+/// returns its transactions and receipts roots.
+pub fn verify_header(header_rlp: &[u8], block_number: u64, block_hash: &[u8; 32]) -> EthHeaderRoots {
+    require!(&env::keccak256_array(header_rlp) == block_hash, "Header does not match block hash");
+    let header = RlpItem::decode(header_rlp)
+        .and_then(|item| item.list())
+        .unwrap_or_else(|| env::panic_str("Invalid block header"));
+    let number = header
+        .get(HEADER_NUMBER_INDEX)
+        .and_then(RlpItem::as_u64)
+        .unwrap_or_else(|| env::panic_str("Invalid block header"));
+    require!(number == block_number, "Header is for a different block");
+    let root = |index: usize| {
+        header
+            .get(index)
+            .and_then(RlpItem::bytes)
+            .and_then(|root| <[u8; 32]>::try_from(root).ok())
+            .unwrap_or_else(|| env::panic_str("Invalid block header"))
+    };
+    EthHeaderRoots {
+        transactions_root: root(HEADER_TRANSACTIONS_ROOT_INDEX),
+        receipts_root: root(HEADER_RECEIPTS_ROOT_INDEX),
+    }
+}
+
+/// Walks a Merkle-Patricia proof from `root` along `key` and returns the value
+/// stored at the leaf.
+pub fn verify_trie_proof(root: &[u8; 32], key: &[u8], proof: &[Vec<u8>]) -> Vec<u8> {
+    let key: Vec<u8> = key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect();
+    let mut key_index = 0;
+    let mut expected_hash: Option<[u8; 32]> = Some(*root);
+    let mut inline_node: Option<Vec<u8>> = None;
+    let mut nodes = proof.iter();
+
+    loop {
+        let node_rlp = match (inline_node.take(), expected_hash.take()) {
+            (Some(node), _) => node,
+            (None, Some(hash)) => {
+                let node = nodes.next().unwrap_or_else(|| env::panic_str("Incomplete trie proof"));
+                require!(env::keccak256_array(node) == hash, "Invalid trie proof node");
+                node.clone()
+            }
+            (None, None) => env::panic_str("Invalid trie proof"),
+        };
+        let node = RlpItem::decode(&node_rlp)
+            .and_then(|item| item.list())
+            .unwrap_or_else(|| env::panic_str("Invalid trie node"));
+
+        let next = match node.len() {
+            17 => {
+                if key_index == key.len() {
+                    return node[16].bytes().unwrap_or_else(|| env::panic_str("Invalid trie node")).to_vec();
+                }
+                let child = node[usize::from(key[key_index])];
+                key_index += 1;
+                child
+            }
+            2 => {
+                let encoded_path = node[0].bytes().unwrap_or_else(|| env::panic_str("Invalid trie node"));
+                let (path, is_leaf) = decode_hex_prefix(encoded_path);
+                require!(key[key_index..].starts_with(&path), "Key not in trie");
+                key_index += path.len();
+                if is_leaf {
+                    require!(key_index == key.len(), "Key not in trie");
+                    return node[1].bytes().unwrap_or_else(|| env::panic_str("Invalid trie node")).to_vec();
+                }
+                node[1]
+            }
+            _ => env::panic_str("Invalid trie node"),
+        };
+
+        if next.is_list {
+            inline_node = Some(next.raw.to_vec());
+        } else {
+            let hash = <[u8; 32]>::try_from(next.payload).unwrap_or_else(|_| env::panic_str("Key not in trie"));
+            expected_hash = Some(hash);
+        }
+    }
+}
+
+fn decode_hex_prefix(encoded: &[u8]) -> (Vec<u8>, bool) {
+    let first = *encoded.first().unwrap_or_else(|| env::panic_str("Invalid trie node"));
+    let flag = first >> 4;
+    let is_leaf = flag & 0x2 != 0;
+    let mut path = Vec::with_capacity(encoded.len() * 2);
+    if flag & 0x1 != 0 {
+        path.push(first & 0x0f);
+    }
+    for b in &encoded[1..] {
+        path.push(b >> 4);
+        path.push(b & 0x0f);
+    }
+    (path, is_leaf)
+}
+
+/// Extracts log `log_index` from an RLP receipt, accepting both legacy and
+/// EIP-2718 typed receipts.
+pub fn decode_receipt_log(receipt: &[u8], log_index: u64) -> EthLog {
+    let receipt = match receipt.first() {
+        Some(tx_type) if *tx_type < 0x7f => &receipt[1..],
+        _ => receipt,
+    };
+    let fields = RlpItem::decode(receipt)
+        .and_then(|item| item.list())
+        .unwrap_or_else(|| env::panic_str("Invalid receipt"));
+    let logs = fields
+        .get(3)
+        .and_then(RlpItem::list)
+        .unwrap_or_else(|| env::panic_str("Invalid receipt"));
+    let log = usize::try_from(log_index)
+        .ok()
+        .and_then(|index| logs.get(index))
+        .and_then(RlpItem::list)
+        .unwrap_or_else(|| env::panic_str("Log not found in receipt"));
+    require!(log.len() == 3, "Invalid log");
+
+    let address = log[0]
+        .bytes()
+        .and_then(|address| <[u8; 20]>::try_from(address).ok())
+        .unwrap_or_else(|| env::panic_str("Invalid log"));
+    let topics = log[1]
+        .list()
+        .unwrap_or_else(|| env::panic_str("Invalid log"))
+        .iter()
+        .map(|topic| {
+            topic
+                .bytes()
+                .and_then(|topic| <[u8; 32]>::try_from(topic).ok())
+                .unwrap_or_else(|| env::panic_str("Invalid log"))
+        })
+        .collect();
+    let data = log[2].bytes().unwrap_or_else(|| env::panic_str("Invalid log")).to_vec();
+    EthLog { address, topics, data }
+}
+
+/// Verifies `proof` against the header of `block_number` whose hash the light
+/// client reported as `block_hash`, and returns the proven log with the hash
+/// of the transaction that emitted it.
+pub fn prove_log(proof: &EthReceiptProof, block_number: u64, block_hash: &[u8; 32]) -> (EthLog, [u8; 32]) {
+    let roots = verify_header(&proof.header_rlp, block_number, block_hash);
+    let key = rlp_encode_u64(proof.receipt_index);
+    let receipt = verify_trie_proof(&roots.receipts_root, &key, &proof.receipt_proof);
+    // Trie values are the transaction's canonical encoding, typed or legacy,
+    // which is what its hash covers.
+    let transaction = verify_trie_proof(&roots.transactions_root, &key, &proof.transaction_proof);
+    (decode_receipt_log(&receipt, proof.log_index), env::keccak256_array(&transaction))
+}
@@ -0,0 +1,308 @@
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use near_sdk::serde_json;
+use near_sdk::{env, near, AccountId};
+
+use crate::{CanonicalAssetId, ChainConfig, ChainId, MessageFee, SecurityModel, ValidatorKey};
+
+pub const EVENT_JSON_PREFIX: &str = "EVENT_JSON:";
+pub const EVENT_STANDARD: &str = "nep_xchain";
+pub const EVENT_VERSION: &str = "1.1.0";
+
+#[near(serializers = [json])]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum NepXChainEvent {
+    BridgeIn {
+        canonical_asset: CanonicalAssetId,
+        amount: U128,
+        receiver_id: AccountId,
+        source_tx_hash: String,
+        receipt_token: AccountId,
+    },
+    BridgeOut {
+        canonical_asset: CanonicalAssetId,
+        amount: U128,
+        sender_id: AccountId,
+        destination_chain: ChainId,
+        destination_address: String,
+        receipt_token: AccountId,
+    },
+    ValidatorAdded {
+        caller: AccountId,
+        public_key: ValidatorKey,
+        weight: u64,
+    },
+    ValidatorRemoved {
+        caller: AccountId,
+        public_key: ValidatorKey,
+    },
+    ValidatorSetRotated {
+        caller: AccountId,
+        old_epoch: u64,
+        new_epoch: u64,
+        validators: Vec<ValidatorKey>,
+        quorum: u64,
+    },
+    QuorumChanged {
+        caller: AccountId,
+        epoch: u64,
+        old_quorum: u64,
+        new_quorum: u64,
+    },
+    EpochGracePeriodChanged {
+        caller: AccountId,
+        old_grace_period: u64,
+        new_grace_period: u64,
+    },
+    MaxValidatorsChanged {
+        caller: AccountId,
+        old_max_validators: u32,
+        new_max_validators: u32,
+    },
+    ValidatorBonded {
+        caller: AccountId,
+        public_key: ValidatorKey,
+        amount: U128,
+        total_staked: U128,
+    },
+    ValidatorUnbonding {
+        caller: AccountId,
+        public_key: ValidatorKey,
+        amount: U128,
+        unlock_at: u64,
+    },
+    StakeWithdrawn {
+        caller: AccountId,
+        public_key: ValidatorKey,
+        amount: U128,
+    },
+    ValidatorSlashed {
+        reporter: AccountId,
+        public_key: ValidatorKey,
+        amount: U128,
+        source_tx_hash: String,
+        nonce: U128,
+    },
+    UnbondingPeriodChanged {
+        caller: AccountId,
+        old_unbonding_period: u64,
+        new_unbonding_period: u64,
+    },
+    MinValidatorBondChanged {
+        caller: AccountId,
+        old_min_bond: U128,
+        new_min_bond: U128,
+    },
+    SupportedChainChanged {
+        caller: AccountId,
+        chain_id: ChainId,
+        config: Option<ChainConfig>,
+    },
+    StrictOrderingChanged {
+        caller: AccountId,
+        source_chain: ChainId,
+        next_nonce: Option<U128>,
+    },
+    NonceWatermarkRaised {
+        caller: AccountId,
+        source_chain: ChainId,
+        old_watermark: U128,
+        new_watermark: U128,
+    },
+    EthProverChanged {
+        caller: AccountId,
+        old_light_client: Option<AccountId>,
+        new_light_client: Option<AccountId>,
+    },
+    Paused {
+        caller: AccountId,
+        old_paused: bool,
+        new_paused: bool,
+    },
+    TreasuryChanged {
+        caller: AccountId,
+        old_treasury: AccountId,
+        new_treasury: AccountId,
+    },
+    ReceiptTokenCodeChanged {
+        caller: AccountId,
+        old_code_hash: Option<Base58CryptoHash>,
+        new_code_hash: Option<Base58CryptoHash>,
+    },
+    ReceiptTokenUpgraded {
+        caller: AccountId,
+        receipt_token: AccountId,
+        code_hash: Base58CryptoHash,
+    },
+    MessageSent {
+        destination_chain: ChainId,
+        destination_contract: String,
+        nonce: U128,
+        sender: AccountId,
+        payload_hash: Vec<u8>,
+    },
+    MessageFeeChanged {
+        caller: AccountId,
+        destination_chain: ChainId,
+        old_fee: Option<MessageFee>,
+        new_fee: Option<MessageFee>,
+    },
+    FeesWithdrawn {
+        caller: AccountId,
+        treasury: AccountId,
+        amount: U128,
+    },
+    PrepaidGasWithdrawn {
+        caller: AccountId,
+        treasury: AccountId,
+        amount: U128,
+    },
+    SenderAllowlistChanged {
+        caller: AccountId,
+        destination_chain: ChainId,
+        destination_contract: String,
+        senders: Option<Vec<AccountId>>,
+    },
+    MessageReceived {
+        source_chain: ChainId,
+        source_contract: String,
+        nonce: U128,
+        destination_contract: String,
+        payload_hash: Vec<u8>,
+    },
+    BridgeRouteChanged {
+        security_model: SecurityModel,
+    },
+    AssetRegistered {
+        canonical_asset: CanonicalAssetId,
+        receipt_token: AccountId,
+    },
+    LiquidityAdded {
+        account_id: AccountId,
+        receipt_token: AccountId,
+        amount: U128,
+    },
+    LiquidityRemoved {
+        account_id: AccountId,
+        receipt_token: AccountId,
+        amount: U128,
+    },
+    ReceiptTokenSwapped {
+        account_id: AccountId,
+        token_in: AccountId,
+        token_out: AccountId,
+        amount: U128,
+    },
+    BridgeRegistered {
+        bridge_contract: AccountId,
+        security_model: SecurityModel,
+    },
+    BridgeDeregistered {
+        caller: AccountId,
+        bridge_contract: AccountId,
+    },
+    ReceiptVerified {
+        receipt_token: AccountId,
+        bridge_contract: AccountId,
+        verified: bool,
+    },
+}
+
+#[near(serializers = [json])]
+struct EventLog {
+    standard: String,
+    version: String,
+    #[serde(flatten)]
+    event: NepXChainEvent,
+}
+
+impl NepXChainEvent {
+    pub fn to_log_string(&self) -> String {
+        let log = EventLog {
+            standard: EVENT_STANDARD.to_string(),
+            version: EVENT_VERSION.to_string(),
+            event: self.clone(),
+        };
+        format!(
+            "{}{}",
+            EVENT_JSON_PREFIX,
+            serde_json::to_string(&log).expect("Failed to serialize event")
+        )
+    }
+
+    pub fn emit(&self) {
+        env::log_str(&self.to_log_string());
+    }
+
+    /// Decodes a log line produced by `emit`; `None` for any other log.
+    pub fn from_log(log: &str) -> Option<Self> {
+        let json = log.strip_prefix(EVENT_JSON_PREFIX)?;
+        let log: EventLog = serde_json::from_str(json).ok()?;
+        if log.standard != EVENT_STANDARD {
+            return None;
+        }
+        Some(log.event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AssetStandard;
+
+    fn bridge_in() -> NepXChainEvent {
+        NepXChainEvent::BridgeIn {
+            canonical_asset: CanonicalAssetId {
+                source_chain_id: "eip155:1".parse().unwrap(),
+                source_contract: "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+                asset_standard: AssetStandard::ERC20,
+            },
+            amount: U128(1_000_000),
+            receiver_id: "alice.near".parse().unwrap(),
+            source_tx_hash: "0xabc".to_string(),
+            receipt_token: "usdc.bridge.near".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn log_string_round_trip() {
+        let events = [
+            bridge_in(),
+            NepXChainEvent::Paused {
+                caller: "owner.near".parse().unwrap(),
+                old_paused: false,
+                new_paused: true,
+            },
+        ];
+        for event in events {
+            let log = event.to_log_string();
+            assert!(log.starts_with(EVENT_JSON_PREFIX));
+            assert_eq!(NepXChainEvent::from_log(&log), Some(event));
+        }
+    }
+
+    #[test]
+    fn log_string_format() {
+        let log = NepXChainEvent::Paused {
+            caller: "owner.near".parse().unwrap(),
+            old_paused: false,
+            new_paused: true,
+        }
+        .to_log_string();
+        assert_eq!(
+            log,
+            format!(
+                r#"EVENT_JSON:{{"standard":"nep_xchain","version":"{EVENT_VERSION}","event":"paused","data":{{"caller":"owner.near","old_paused":false,"new_paused":true}}}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn foreign_standard_rejected() {
+        let log = bridge_in().to_log_string().replace(r#""standard":"nep_xchain""#, r#""standard":"nep171""#);
+        assert!(log.contains("nep171"));
+        assert_eq!(NepXChainEvent::from_log(&log), None);
+        assert_eq!(NepXChainEvent::from_log("Transfer 1 from alice.near"), None);
+        assert_eq!(NepXChainEvent::from_log("EVENT_JSON:not json"), None);
+    }
+}
@@ -1,26 +1,31 @@
 use near_sdk::near;
-use near_sdk::{AccountId, Promise, PublicKey, Timestamp};
-use near_sdk::json_types::U128;
-
-pub type ChainId = String;
-
-#[near(serializers = [json, borsh])]
-#[derive(Clone, Debug, PartialEq)]
-pub struct CanonicalAssetId {
-    pub source_chain_id: ChainId,
-    pub source_contract: String,
-    pub asset_standard: AssetStandard,
+use near_sdk::{env, AccountId, Promise, PromiseError, Timestamp};
+use near_sdk::json_types::{Base58CryptoHash, U128};
+
+pub mod address;
+pub mod asset;
+pub mod chain;
+pub mod committee;
+pub mod eth;
+pub mod events;
+pub mod nonces;
+
+pub use asset::{AssetStandard, CanonicalAssetId};
+pub use chain::{AddressFormat, ChainConfig, ChainId};
+pub use committee::ValidatorKey;
+
+/// Longest result read back from a cross-contract call.
+pub const MAX_PROMISE_RESULT_LEN: usize = 16 * 1024;
+
+/// The value returned by promise `index`, or `None` if it failed or returned
+/// more than `MAX_PROMISE_RESULT_LEN` bytes.
+pub fn promise_value(index: u64) -> Option<Vec<u8>> {
+    env::promise_result_checked(index, MAX_PROMISE_RESULT_LEN).ok()
 }
 
-#[near(serializers = [json, borsh])]
-#[derive(Clone, Debug, PartialEq)]
-pub enum AssetStandard {
-    Native,
-    ERC20,
-    ERC721,
-    ERC1155,
-    SPL,
-    Custom(String),
+/// Whether promise `index` succeeded, without reading what it returned.
+pub fn promise_succeeded(index: u64) -> bool {
+    !matches!(env::promise_result_checked(index, 0), Err(PromiseError::Failed))
 }
 
 #[near(serializers = [json, borsh])]
@@ -38,17 +43,33 @@ pub enum SecurityModel {
     ZeroKnowledge { proof_system: String },
     Committee { threshold: u8, size: u8 },
     Trusted { operator: AccountId },
+    WeightedCommittee { quorum: u64, total_weight: u64, size: u32 },
 }
 
 #[near(serializers = [json, borsh])]
 #[derive(Clone, Debug)]
 pub struct ReceiptTokenMetadata {
     pub canonical_asset: CanonicalAssetId,
+    /// Route of the bridge that deployed the token; it is also listed in
+    /// `bridge_routes`.
     pub bridge_route: BridgeRoute,
+    /// Missing from tokens that have not been migrated to multiple routes.
+    #[serde(default)]
+    pub bridge_routes: Vec<AuthorizedRoute>,
     pub total_locked: U128,
     pub nep141_token: AccountId,
 }
 
+/// A bridge allowed to mint and burn a receipt token. `supply` is what this
+/// route has minted and not yet burned; a route can only burn up to it.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthorizedRoute {
+    pub route: BridgeRoute,
+    pub destination_chains: Vec<ChainId>,
+    pub supply: U128,
+}
+
 #[near(serializers = [json, borsh])]
 #[derive(Clone, Debug)]
 pub enum BridgeDirection {
@@ -62,13 +83,15 @@ pub struct BridgeProof {
     pub source_tx_hash: String,
     pub proof_data: Vec<u8>,
     pub block_height: u64,
+    pub committee_proof: Option<CommitteeProof>,
+    pub eth_proof: Option<eth::EthReceiptProof>,
 }
 
 #[near(serializers = [json, borsh])]
 #[derive(Clone, Debug)]
 pub struct CommitteeProof {
-    pub signatures: Vec<(PublicKey, Vec<u8>)>,
-    pub message_hash: Vec<u8>,
+    pub epoch: u64,
+    pub signatures: Vec<(ValidatorKey, Vec<u8>)>,
 }
 
 #[near(serializers = [json, borsh])]
@@ -80,6 +103,51 @@ pub struct XChainMessage {
     pub source_contract: String,
     pub destination_contract: String,
     pub payload: Vec<u8>,
+    /// Trails the original fields in the borsh encoding, which messages
+    /// without it no longer match.
+    pub ordering: MessageOrdering,
+}
+
+/// How the destination executes a message. `Unordered` messages only need a
+/// fresh `(source_chain, nonce)`; `Ordered` ones must also arrive in
+/// `sequence` order within their `(source_contract, destination_contract)`
+/// channel.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub enum MessageOrdering {
+    Unordered,
+    Ordered { sequence: U128 },
+}
+
+impl XChainMessage {
+    /// sha256 of the borsh encoding, which is what validators sign.
+    pub fn hash(&self) -> Base58CryptoHash {
+        near_sdk::env::sha256_array(near_sdk::borsh::to_vec(self).expect("Failed to serialize XChainMessage")).into()
+    }
+}
+
+/// Price of sending a message to one destination chain, in yoctoNEAR:
+/// `base_fee + per_byte_fee * payload.len()`.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageFee {
+    pub base_fee: U128,
+    pub per_byte_fee: U128,
+}
+
+impl MessageFee {
+    pub fn for_payload(&self, payload_len: u64) -> u128 {
+        self.base_fee.0 + self.per_byte_fee.0 * u128::from(payload_len)
+    }
+}
+
+/// Returned by `send_message` to identify the outbound message.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageReceipt {
+    pub destination_chain: ChainId,
+    pub nonce: U128,
+    pub message_hash: Base58CryptoHash,
 }
 
 #[near(serializers = [json, borsh])]
@@ -94,6 +162,26 @@ pub struct BridgePayload {
     pub source_tx_hash: String,
 }
 
+/// An inbound transfer that is delivered with `ft_transfer_call(receiver, amount, msg)`
+/// when `msg` is set, e.g. to bridge and deposit into a lending pool in one hop.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct TokenCallPayload {
+    pub transfer: BridgePayload,
+    pub msg: Option<String>,
+}
+
+impl TokenCallPayload {
+    /// Decodes signed proof data holding either a `TokenCallPayload` or a plain
+    /// `BridgePayload`, which is delivered without a call.
+    pub fn from_proof_data(data: &[u8]) -> Option<Self> {
+        if let Ok(transfer) = near_sdk::borsh::from_slice::<BridgePayload>(data) {
+            return Some(Self { transfer, msg: None });
+        }
+        near_sdk::borsh::from_slice(data).ok()
+    }
+}
+
 pub trait XChainCore {
     fn bridge_in(
         &mut self,
@@ -122,8 +210,14 @@ pub trait XChainCore {
     fn set_paused(&mut self, paused: bool);
     fn is_paused(&self) -> bool;
     
-    fn add_validator(&mut self, public_key: PublicKey);
-    fn remove_validator(&mut self, public_key: PublicKey);
+    fn add_validator(
+        &mut self,
+        public_key: ValidatorKey,
+        weight: Option<u64>,
+        operator_id: Option<AccountId>,
+        name: Option<String>,
+    );
+    fn remove_validator(&mut self, public_key: ValidatorKey);
 }
 
 pub trait XChainReceiptToken {
@@ -138,7 +232,8 @@ pub trait XChainMessaging {
         destination_chain: ChainId,
         destination_contract: String,
         payload: Vec<u8>,
-    ) -> Promise;
+        ordered: Option<bool>,
+    ) -> MessageReceipt;
 
     fn receive_message(
         &mut self,
@@ -0,0 +1,169 @@
+use near_sdk::json_types::U128;
+use near_sdk::store::LookupMap;
+use near_sdk::{env, near, require, IntoStorageKey};
+
+use crate::ChainId;
+
+const WORD_BITS: u128 = 128;
+// Per-record overhead NEAR charges on top of key and value bytes.
+const RECORD_OVERHEAD: u64 = 40;
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct NonceStorageUsage {
+    pub chains: u64,
+    pub bitmap_words: u64,
+    pub storage_bytes: u64,
+    pub storage_cost: U128,
+}
+
+#[near(serializers = [borsh])]
+#[derive(Clone, Copy, Debug, Default)]
+struct ChainNonces {
+    watermark: u128,
+    words: u32,
+}
+
+/// Processed nonces per chain: a watermark below which every nonce is used,
+/// plus 128-bit bitmap words for nonces that arrived ahead of it. Words are
+/// deleted as soon as the watermark passes them, so in-order traffic keeps one
+/// entry per chain.
+#[near(serializers = [borsh])]
+pub struct NonceTracker {
+    prefix: Vec<u8>,
+    watermarks: LookupMap<ChainId, ChainNonces>,
+    words: LookupMap<(ChainId, u128), u128>,
+    chains: u64,
+    word_count: u64,
+    storage_bytes: u64,
+}
+
+impl NonceTracker {
+    pub fn new<S: IntoStorageKey>(prefix: S) -> Self {
+        let prefix = prefix.into_storage_key();
+        Self {
+            watermarks: LookupMap::new([prefix.as_slice(), b"w"].concat()),
+            words: LookupMap::new([prefix.as_slice(), b"b"].concat()),
+            prefix,
+            chains: 0,
+            word_count: 0,
+            storage_bytes: 0,
+        }
+    }
+
+    /// Lowest nonce of `chain` that has not been processed.
+    pub fn watermark(&self, chain: &ChainId) -> u128 {
+        self.chain(chain).watermark
+    }
+
+    /// Starts tracking `chain` with `first_nonce` as its watermark; no-op if the
+    /// chain is already tracked.
+    pub fn start_at(&mut self, chain: &ChainId, first_nonce: u128) {
+        if !self.watermarks.contains_key(chain) {
+            self.save_chain(chain, ChainNonces { watermark: first_nonce, words: 0 });
+        }
+    }
+
+    /// Marks every nonce of `chain` below `watermark` as processed, e.g. when a
+    /// source chain's nonces start above zero. Only allowed while no nonces are
+    /// pending above the current watermark.
+    pub fn raise_watermark(&mut self, chain: &ChainId, watermark: u128) {
+        let state = self.chain(chain);
+        require!(watermark >= state.watermark, "Watermark can only move forward");
+        require!(state.words == 0, "Nonces pending above watermark");
+        self.save_chain(chain, ChainNonces { watermark, words: 0 });
+    }
+
+    pub fn is_processed(&self, chain: &ChainId, nonce: u128) -> bool {
+        if nonce < self.watermark(chain) {
+            return true;
+        }
+        let word = self.words.get(&(chain.clone(), nonce / WORD_BITS)).copied().unwrap_or(0);
+        word & (1 << (nonce % WORD_BITS)) != 0
+    }
+
+    /// Records `nonce` for `chain`; returns `false` if it was already processed.
+    pub fn mark(&mut self, chain: &ChainId, nonce: u128) -> bool {
+        if self.is_processed(chain, nonce) {
+            return false;
+        }
+        let mut state = self.chain(chain);
+        if nonce == state.watermark {
+            state.watermark += 1;
+            self.advance(chain, &mut state);
+        } else {
+            let key = (chain.clone(), nonce / WORD_BITS);
+            let word = self.words.get(&key).copied().unwrap_or(0);
+            self.set_word(&mut state, key, word | (1 << (nonce % WORD_BITS)));
+        }
+        self.save_chain(chain, state);
+        true
+    }
+
+    pub fn storage_usage(&self) -> NonceStorageUsage {
+        NonceStorageUsage {
+            chains: self.chains,
+            bitmap_words: self.word_count,
+            storage_bytes: self.storage_bytes,
+            storage_cost: U128(env::storage_byte_cost().as_yoctonear() * u128::from(self.storage_bytes)),
+        }
+    }
+
+    fn chain(&self, chain: &ChainId) -> ChainNonces {
+        self.watermarks.get(chain).copied().unwrap_or_default()
+    }
+
+    fn save_chain(&mut self, chain: &ChainId, state: ChainNonces) {
+        if self.watermarks.insert(chain.clone(), state).is_none() {
+            self.chains += 1;
+            self.storage_bytes += self.record_bytes(chain) + 20;
+        }
+    }
+
+    // Pulls the watermark over bitmap words that are already filled in.
+    fn advance(&mut self, chain: &ChainId, state: &mut ChainNonces) {
+        while state.words > 0 {
+            let key = (chain.clone(), state.watermark / WORD_BITS);
+            let Some(word) = self.words.get(&key).copied() else {
+                break;
+            };
+            let offset = state.watermark % WORD_BITS;
+            let run = u128::from((word >> offset).trailing_ones());
+            state.watermark += run;
+            if offset + run == WORD_BITS {
+                self.set_word(state, key, 0);
+                continue;
+            }
+            self.set_word(state, key, word & !((1 << (offset + run)) - 1));
+            break;
+        }
+    }
+
+    fn set_word(&mut self, state: &mut ChainNonces, key: (ChainId, u128), word: u128) {
+        let record_bytes = self.record_bytes(&key.0) + 32;
+        let existed = if word == 0 {
+            self.words.remove(&key).is_some()
+        } else {
+            self.words.insert(key, word).is_some()
+        };
+        match (existed, word == 0) {
+            (false, false) => {
+                state.words += 1;
+                self.word_count += 1;
+                self.storage_bytes += record_bytes;
+            }
+            (true, true) => {
+                state.words -= 1;
+                self.word_count -= 1;
+                self.storage_bytes -= record_bytes;
+            }
+            _ => {}
+        }
+    }
+
+    // Key bytes (prefix, map tag, borsh chain id) plus record overhead; callers
+    // add the rest of the key and the value.
+    fn record_bytes(&self, chain: &ChainId) -> u64 {
+        (self.prefix.len() + 1 + 4 + chain.as_str().len()) as u64 + RECORD_OVERHEAD
+    }
+}
@@ -0,0 +1,107 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near, require, AccountId, NearToken, Promise};
+use xchain_core::events::NepXChainEvent;
+use xchain_core::{ChainId, MessageFee};
+
+use crate::{XChainMessenger, XChainMessengerExt};
+
+#[near]
+impl XChainMessenger {
+    /// Sets the fee for messages to `destination_chain`. `None` makes sending
+    /// there free.
+    pub fn set_message_fee(&mut self, destination_chain: ChainId, fee: Option<MessageFee>) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let old_fee = match &fee {
+            Some(fee) => self.message_fees.insert(destination_chain.clone(), fee.clone()),
+            None => self.message_fees.remove(&destination_chain),
+        };
+        NepXChainEvent::MessageFeeChanged {
+            caller: env::predecessor_account_id(),
+            destination_chain,
+            old_fee,
+            new_fee: fee,
+        }.emit();
+    }
+
+    pub fn get_message_fee(&self, destination_chain: ChainId) -> Option<MessageFee> {
+        self.message_fees.get(&destination_chain).cloned()
+    }
+
+    /// The fee for a `payload_size`-byte payload. `send_message` needs it plus
+    /// the storage cost of the stored message and of any nonce or sequence
+    /// record it creates; anything attached above both
+    /// is recorded as prepaid destination gas.
+    pub fn estimate_message_fee(&self, destination_chain: ChainId, payload_size: u64) -> U128 {
+        U128(self.message_fee(&destination_chain, payload_size))
+    }
+
+    pub fn set_treasury(&mut self, treasury: AccountId) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let old_treasury = std::mem::replace(&mut self.treasury, treasury.clone());
+        NepXChainEvent::TreasuryChanged {
+            caller: env::predecessor_account_id(),
+            old_treasury,
+            new_treasury: treasury,
+        }.emit();
+    }
+
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury.clone()
+    }
+
+    pub fn get_collected_fees(&self) -> U128 {
+        self.collected_fees
+    }
+
+    pub fn get_prepaid_gas(&self) -> U128 {
+        self.prepaid_gas
+    }
+
+    /// Sends `amount` (default: everything) of the collected fees to the
+    /// treasury.
+    pub fn withdraw_fees(&mut self, amount: Option<U128>) -> Promise {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let amount = amount.unwrap_or(self.collected_fees);
+        require!(amount.0 > 0, "Nothing to withdraw");
+        require!(amount.0 <= self.collected_fees.0, "Amount exceeds collected fees");
+        self.collected_fees = U128(self.collected_fees.0 - amount.0);
+        NepXChainEvent::FeesWithdrawn {
+            caller: env::predecessor_account_id(),
+            treasury: self.treasury.clone(),
+            amount,
+        }.emit();
+        self.pay_treasury(amount)
+    }
+
+    /// Sends `amount` (default: everything) of the prepaid destination gas to
+    /// the treasury, which compensates relayers.
+    pub fn withdraw_prepaid_gas(&mut self, amount: Option<U128>) -> Promise {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let amount = amount.unwrap_or(self.prepaid_gas);
+        require!(amount.0 > 0, "Nothing to withdraw");
+        require!(amount.0 <= self.prepaid_gas.0, "Amount exceeds prepaid gas");
+        self.prepaid_gas = U128(self.prepaid_gas.0 - amount.0);
+        NepXChainEvent::PrepaidGasWithdrawn {
+            caller: env::predecessor_account_id(),
+            treasury: self.treasury.clone(),
+            amount,
+        }.emit();
+        self.pay_treasury(amount)
+    }
+}
+
+impl XChainMessenger {
+    /// Pays out of the contract balance, never below what its storage needs.
+    fn pay_treasury(&self, amount: U128) -> Promise {
+        let storage_balance = env::storage_usage() as u128 * env::storage_byte_cost().as_yoctonear();
+        let available = env::account_balance().as_yoctonear().saturating_sub(storage_balance);
+        require!(amount.0 <= available, "Amount exceeds available balance");
+        Promise::new(self.treasury.clone()).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    pub(crate) fn message_fee(&self, destination_chain: &ChainId, payload_size: u64) -> u128 {
+        self.message_fees
+            .get(destination_chain)
+            .map_or(0, |fee| fee.for_payload(payload_size))
+    }
+}
@@ -1,15 +1,49 @@
-use near_sdk::{near, env, require, AccountId, Promise, PublicKey};
-use near_sdk::store::{IterableSet, LookupMap};
+use near_sdk::{near, env, require, AccountId};
+use near_sdk::borsh::BorshSerialize;
+use near_sdk::store::LookupMap;
 use near_sdk::json_types::U128;
-use xchain_core::{ChainId, BridgeProof, XChainMessaging, XChainMessage};
+use xchain_core::{ChainId, BridgeProof, CommitteeProof, MessageFee, MessageOrdering, MessageReceipt, ValidatorKey, XChainMessaging, XChainMessage};
+use xchain_core::asset::normalize_contract;
+use xchain_core::committee::{Committee, ValidatorConfig, ValidatorEpoch};
+use xchain_core::events::NepXChainEvent;
+use xchain_core::nonces::{NonceStorageUsage, NonceTracker};
+
+mod fees;
+mod migration;
+
+const OUTBOUND_MESSAGES_PREFIX: &[u8] = b"m";
+const DESTINATION_NONCES_PREFIX: &[u8] = b"d";
+const OUTBOUND_SEQUENCES_PREFIX: &[u8] = b"o";
 
 #[near(contract_state)]
 pub struct XChainMessenger {
     pub owner_id: AccountId,
-    pub validators: IterableSet<PublicKey>,
+    pub committee: Committee,
     pub processed_nonces: LookupMap<ChainId, U128>,
     pub destination_nonces: LookupMap<ChainId, U128>,
     pub paused: bool,
+    pub chain_id: ChainId,
+    pub outbound_messages: LookupMap<(ChainId, u128), OutboundMessage>,
+    pub sender_allowlists: LookupMap<(ChainId, String), Vec<AccountId>>,
+    pub treasury: AccountId,
+    pub message_fees: LookupMap<ChainId, MessageFee>,
+    pub collected_fees: U128,
+    pub prepaid_gas: U128,
+    pub received: NonceTracker,
+    pub outbound_sequences: LookupMap<(ChainId, String, String), u128>,
+    pub inbound_sequences: LookupMap<(ChainId, String, String), u128>,
+}
+
+/// A sent message with what its sender paid. `storage_cost` backs the stored
+/// message and any nonce or sequence record it created, and `prepaid_gas` is the rest of the deposit above `fee`,
+/// owed to the relayer for destination gas.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct OutboundMessage {
+    pub message: XChainMessage,
+    pub fee: U128,
+    pub storage_cost: U128,
+    pub prepaid_gas: U128,
 }
 
 impl Default for XChainMessenger {
@@ -21,96 +55,303 @@ impl Default for XChainMessenger {
 #[near]
 impl XChainMessenger {
     #[init]
-    pub fn new(owner_id: AccountId, initial_validators: Vec<PublicKey>) -> Self {
-        let mut validators = IterableSet::new(b"v");
-        for v in initial_validators {
-            validators.insert(v);
-        }
+    pub fn new(owner_id: AccountId, initial_validators: Vec<ValidatorKey>, chain_id: ChainId) -> Self {
         Self {
+            treasury: owner_id.clone(),
             owner_id,
-            validators,
+            committee: Committee::new(b"v", initial_validators),
             processed_nonces: LookupMap::new(b"n"),
-            destination_nonces: LookupMap::new(b"d"),
+            destination_nonces: LookupMap::new(DESTINATION_NONCES_PREFIX),
             paused: false,
+            chain_id,
+            outbound_messages: LookupMap::new(OUTBOUND_MESSAGES_PREFIX),
+            sender_allowlists: LookupMap::new(b"a"),
+            message_fees: LookupMap::new(b"f"),
+            collected_fees: U128(0),
+            prepaid_gas: U128(0),
+            received: NonceTracker::new(b"r"),
+            outbound_sequences: LookupMap::new(OUTBOUND_SEQUENCES_PREFIX),
+            inbound_sequences: LookupMap::new(b"i"),
         }
     }
 
     pub fn set_paused(&mut self, paused: bool) {
         require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
-        self.paused = paused;
+        let old_paused = std::mem::replace(&mut self.paused, paused);
+        NepXChainEvent::Paused {
+            caller: env::predecessor_account_id(),
+            old_paused,
+            new_paused: paused,
+        }.emit();
+    }
+
+    pub fn add_validator(
+        &mut self,
+        pk: ValidatorKey,
+        weight: Option<u64>,
+        operator_id: Option<AccountId>,
+        name: Option<String>,
+    ) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let weight = weight.unwrap_or(1);
+        self.committee.add_validator(ValidatorConfig {
+            public_key: pk.clone(),
+            weight,
+            operator_id,
+            name,
+        });
+        NepXChainEvent::ValidatorAdded {
+            caller: env::predecessor_account_id(),
+            public_key: pk,
+            weight,
+        }.emit();
+    }
+
+    pub fn remove_validator(&mut self, pk: ValidatorKey) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        self.committee.remove_validator(&pk);
+        NepXChainEvent::ValidatorRemoved {
+            caller: env::predecessor_account_id(),
+            public_key: pk,
+        }.emit();
+    }
+
+    pub fn rotate_validators(&mut self, validators: Vec<ValidatorConfig>, quorum: u64, proof: CommitteeProof) -> u64 {
+        self.committee.rotate(validators, quorum, &proof)
+    }
+
+    pub fn set_quorum(&mut self, quorum: u64) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let old_quorum = self.committee.set_quorum(quorum);
+        NepXChainEvent::QuorumChanged {
+            caller: env::predecessor_account_id(),
+            epoch: self.committee.current_epoch(),
+            old_quorum,
+            new_quorum: quorum,
+        }.emit();
     }
 
-    pub fn add_validator(&mut self, pk: PublicKey) {
+    pub fn set_epoch_grace_period(&mut self, grace_period: u64) {
         require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
-        self.validators.insert(pk);
+        let old_grace_period = self.committee.grace_period();
+        self.committee.set_grace_period(grace_period);
+        NepXChainEvent::EpochGracePeriodChanged {
+            caller: env::predecessor_account_id(),
+            old_grace_period,
+            new_grace_period: grace_period,
+        }.emit();
     }
 
-    pub fn remove_validator(&mut self, pk: PublicKey) {
+    pub fn set_max_validators(&mut self, max_validators: u32) {
         require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
-        self.validators.remove(&pk);
+        let old_max_validators = self.committee.set_max_validators(max_validators);
+        NepXChainEvent::MaxValidatorsChanged {
+            caller: env::predecessor_account_id(),
+            old_max_validators,
+            new_max_validators: max_validators,
+        }.emit();
+    }
+
+    pub fn get_max_validators(&self) -> u32 {
+        self.committee.max_validators()
+    }
+
+    pub fn get_current_epoch(&self) -> u64 {
+        self.committee.current_epoch()
+    }
+
+    pub fn get_validator_epoch(&self, epoch: Option<u64>) -> Option<ValidatorEpoch> {
+        let epoch = epoch.unwrap_or_else(|| self.committee.current_epoch());
+        self.committee.get_epoch(epoch).cloned()
+    }
+
+    /// Restricts which NEAR accounts may send to `destination_contract` on
+    /// `destination_chain`. `None` removes the allowlist, letting anyone send.
+    pub fn set_sender_allowlist(
+        &mut self,
+        destination_chain: ChainId,
+        destination_contract: String,
+        senders: Option<Vec<AccountId>>,
+    ) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let key = (destination_chain.clone(), normalize_contract(&destination_contract));
+        match &senders {
+            Some(senders) => self.sender_allowlists.insert(key, senders.clone()),
+            None => self.sender_allowlists.remove(&key),
+        };
+        NepXChainEvent::SenderAllowlistChanged {
+            caller: env::predecessor_account_id(),
+            destination_chain,
+            destination_contract,
+            senders,
+        }.emit();
+    }
+
+    pub fn get_sender_allowlist(&self, destination_chain: ChainId, destination_contract: String) -> Option<Vec<AccountId>> {
+        self.sender_allowlists
+            .get(&(destination_chain, normalize_contract(&destination_contract)))
+            .cloned()
+    }
+
+    pub fn get_chain_id(&self) -> ChainId {
+        self.chain_id.clone()
+    }
+
+    /// The message sent to `destination_chain` with `nonce`; relayers submit
+    /// `message` to the destination. Its `hash()` is the receipt's
+    /// `message_hash`.
+    pub fn get_outbound_message(&self, destination_chain: ChainId, nonce: U128) -> Option<OutboundMessage> {
+        self.outbound_messages.get(&(destination_chain, nonce.0)).cloned()
+    }
+
+    pub fn is_message_processed(&self, source_chain: ChainId, nonce: U128) -> bool {
+        let legacy = self.processed_nonces.get(&source_chain).map_or(0, |n| n.0);
+        nonce.0 <= legacy || self.received.is_processed(&source_chain, nonce.0)
+    }
+
+    /// Last sequence executed on an ordered inbound channel.
+    pub fn get_inbound_sequence(&self, source_chain: ChainId, source_contract: String, destination_contract: AccountId) -> U128 {
+        let channel = (source_chain, normalize_contract(&source_contract), destination_contract.to_string());
+        U128(self.inbound_sequences.get(&channel).copied().unwrap_or(0))
+    }
+
+    /// Last sequence sent on an ordered outbound channel.
+    pub fn get_outbound_sequence(&self, destination_chain: ChainId, sender: AccountId, destination_contract: String) -> U128 {
+        let channel = (destination_chain, sender.to_string(), normalize_contract(&destination_contract));
+        U128(self.outbound_sequences.get(&channel).copied().unwrap_or(0))
+    }
+
+    pub fn get_received_storage_usage(&self) -> NonceStorageUsage {
+        self.received.storage_usage()
     }
 }
 
 #[near]
 impl XChainMessaging for XChainMessenger {
+    #[payable]
     fn send_message(
         &mut self,
         destination_chain: ChainId,
         destination_contract: String,
         payload: Vec<u8>,
-    ) -> Promise {
+        ordered: Option<bool>,
+    ) -> MessageReceipt {
         require!(!self.paused, "Messenger is paused");
-        
-        let current_nonce = self.destination_nonces.get(&destination_chain).copied().unwrap_or(U128(0));
-        let new_nonce = U128(current_nonce.0 + 1);
+        let sender = env::predecessor_account_id();
+        if let Some(senders) = self
+            .sender_allowlists
+            .get(&(destination_chain.clone(), normalize_contract(&destination_contract)))
+        {
+            require!(senders.contains(&sender), "Sender not allowed for destination");
+        }
+        let fee = self.message_fee(&destination_chain, payload.len() as u64);
+        let attached = env::attached_deposit().as_yoctonear();
+
+        // The first message to a chain, and the first on an ordered channel,
+        // also pay for the counter record they create.
+        let mut counter_storage_cost = 0;
+        let current_nonce = self.destination_nonces.get(&destination_chain).copied();
+        if current_nonce.is_none() {
+            counter_storage_cost += record_storage_cost(DESTINATION_NONCES_PREFIX, &destination_chain, &U128(0));
+        }
+        let new_nonce = U128(current_nonce.map_or(0, |nonce| nonce.0) + 1);
         self.destination_nonces.insert(destination_chain.clone(), new_nonce);
 
-        let payload_hash = env::sha256(&payload);
-        
-        env::log_str(&format!(
-            "EVENT_JSON:{}", 
-            near_sdk::serde_json::json!({
-                "standard": "nep_xchain_msg",
-                "version": "1.0.0",
-                "event": "send_message",
-                "data": {
-                    "destination_chain": destination_chain,
-                    "destination_contract": destination_contract,
-                    "nonce": new_nonce,
-                    "sender": env::predecessor_account_id(),
-                    "payload_hash": payload_hash
-                }
-            })
-        ));
-        
-        Promise::new(env::current_account_id())
+        let ordering = if ordered.unwrap_or(false) {
+            let channel = (destination_chain.clone(), sender.to_string(), normalize_contract(&destination_contract));
+            if !self.outbound_sequences.contains_key(&channel) {
+                counter_storage_cost += record_storage_cost(OUTBOUND_SEQUENCES_PREFIX, &channel, &0u128);
+            }
+            let sequence = self.outbound_sequences.entry(channel).or_insert(0);
+            *sequence += 1;
+            MessageOrdering::Ordered { sequence: U128(*sequence) }
+        } else {
+            MessageOrdering::Unordered
+        };
+
+        let message = XChainMessage {
+            nonce: new_nonce,
+            source_chain: self.chain_id.clone(),
+            destination_chain: destination_chain.clone(),
+            source_contract: sender.to_string(),
+            destination_contract: destination_contract.clone(),
+            payload,
+            ordering,
+        };
+        let message_hash = message.hash();
+
+        NepXChainEvent::MessageSent {
+            destination_chain: destination_chain.clone(),
+            destination_contract,
+            nonce: new_nonce,
+            sender,
+            payload_hash: env::sha256(&message.payload),
+        }.emit();
+
+        let key = (destination_chain.clone(), new_nonce.0);
+        let mut outbound = OutboundMessage {
+            message,
+            fee: U128(fee),
+            storage_cost: U128(0),
+            prepaid_gas: U128(0),
+        };
+        // `U128` encodes to a fixed width, so the placeholders measure the
+        // final record.
+        let storage_cost = record_storage_cost(OUTBOUND_MESSAGES_PREFIX, &key, &outbound) + counter_storage_cost;
+        require!(attached >= fee + storage_cost, "Insufficient message fee");
+        outbound.storage_cost = U128(storage_cost);
+        outbound.prepaid_gas = U128(attached - fee - storage_cost);
+        self.collected_fees = U128(self.collected_fees.0 + fee);
+        self.prepaid_gas = U128(self.prepaid_gas.0 + outbound.prepaid_gas.0);
+        self.outbound_messages.insert(key, outbound);
+        MessageReceipt {
+            destination_chain,
+            nonce: new_nonce,
+            message_hash,
+        }
     }
 
     fn receive_message(&mut self, proof: BridgeProof) {
         require!(!self.paused, "Messenger is paused");
+        let committee_proof = proof.committee_proof.as_ref().expect("Missing committee proof");
+        self.committee.verify(committee_proof, &proof.proof_data);
 
         let message: XChainMessage = near_sdk::borsh::BorshDeserialize::try_from_slice(&proof.proof_data)
             .expect("Failed to deserialize XChainMessage");
+        require!(message.destination_chain == self.chain_id, "Wrong destination chain");
+
+        let source_chain = &message.source_chain;
+        // Chains seen before per-nonce tracking resume after their last
+        // processed nonce; nonces start at 1.
+        let processed = self.processed_nonces.get(source_chain).map_or(0, |n| n.0);
+        self.received.start_at(source_chain, processed + 1);
+        require!(self.received.mark(source_chain, message.nonce.0), "Message already processed");
+
+        if let MessageOrdering::Ordered { sequence } = &message.ordering {
+            let channel = (
+                source_chain.clone(),
+                normalize_contract(&message.source_contract),
+                message.destination_contract.clone(),
+            );
+            let last = self.inbound_sequences.entry(channel).or_insert(0);
+            require!(sequence.0 == *last + 1, "Unexpected channel sequence");
+            *last = sequence.0;
+        }
 
-        let current_nonce = self.processed_nonces.get(&message.source_chain).copied().unwrap_or(U128(0));
-        require!(message.nonce.0 == current_nonce.0 + 1, "Invalid nonce");
-        
-        self.processed_nonces.insert(message.source_chain.clone(), message.nonce);
-
-        env::log_str(&format!(
-            "EVENT_JSON:{}",
-            near_sdk::serde_json::json!({
-                "standard": "nep_xchain_msg",
-                "version": "1.0.0",
-                "event": "receive_message",
-                "data": {
-                    "source_chain": message.source_chain,
-                    "source_contract": message.source_contract,
-                    "nonce": message.nonce,
-                    "destination_contract": message.destination_contract,
-                    "payload_hash": env::sha256(&message.payload)
-                }
-            })
-        ));
+        NepXChainEvent::MessageReceived {
+            payload_hash: env::sha256(&message.payload),
+            source_chain: message.source_chain,
+            source_contract: message.source_contract,
+            nonce: message.nonce,
+            destination_contract: message.destination_contract,
+        }.emit();
     }
 }
+
+/// What storing `value` under `key` in the `LookupMap` at `prefix` costs: the
+/// record's key and value plus the 40 bytes the runtime charges per record.
+fn record_storage_cost<K: BorshSerialize, V: BorshSerialize>(prefix: &[u8], key: &K, value: &V) -> u128 {
+    let key_len = prefix.len() + near_sdk::borsh::to_vec(key).expect("Failed to serialize key").len();
+    let value_len = near_sdk::borsh::to_vec(value).expect("Failed to serialize record").len();
+    (key_len + value_len + 40) as u128 * env::storage_byte_cost().as_yoctonear()
+}
@@ -0,0 +1,51 @@
+use near_sdk::json_types::U128;
+use near_sdk::store::{IterableSet, LookupMap};
+use near_sdk::{env, near, AccountId, PublicKey};
+use xchain_core::committee::Committee;
+use xchain_core::nonces::NonceTracker;
+use xchain_core::{ChainId, ValidatorKey};
+
+use crate::{XChainMessenger, XChainMessengerExt, OUTBOUND_MESSAGES_PREFIX, OUTBOUND_SEQUENCES_PREFIX};
+
+/// The originally deployed layout, with a flat set of validator keys.
+#[near(serializers = [borsh])]
+struct XChainMessengerV1 {
+    owner_id: AccountId,
+    validators: IterableSet<PublicKey>,
+    processed_nonces: LookupMap<ChainId, U128>,
+    destination_nonces: LookupMap<ChainId, U128>,
+    paused: bool,
+}
+
+#[near]
+impl XChainMessenger {
+    /// Upgrades from the originally deployed layout. The validator set becomes
+    /// epoch 0 of the committee; `processed_nonces` is kept so per-chain
+    /// tracking resumes after the last processed nonce.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(chain_id: ChainId) -> Self {
+        let mut old: XChainMessengerV1 = env::state_read().expect("No state to migrate");
+        let validators: Vec<ValidatorKey> = old.validators.iter().cloned().map(ValidatorKey::Near).collect();
+        old.validators.clear();
+        old.validators.flush();
+
+        Self {
+            treasury: old.owner_id.clone(),
+            owner_id: old.owner_id,
+            committee: Committee::new(b"v", validators),
+            processed_nonces: old.processed_nonces,
+            destination_nonces: old.destination_nonces,
+            paused: old.paused,
+            chain_id,
+            outbound_messages: LookupMap::new(OUTBOUND_MESSAGES_PREFIX),
+            sender_allowlists: LookupMap::new(b"a"),
+            message_fees: LookupMap::new(b"f"),
+            collected_fees: U128(0),
+            prepaid_gas: U128(0),
+            received: NonceTracker::new(b"r"),
+            outbound_sequences: LookupMap::new(OUTBOUND_SEQUENCES_PREFIX),
+            inbound_sequences: LookupMap::new(b"i"),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use near_sdk::{near, env, require, AccountId};
+use near_sdk::store::LookupMap;
+
+#[near(contract_state)]
+pub struct MockLightClient {
+    pub owner_id: AccountId,
+    pub block_hashes: LookupMap<u64, [u8; 32]>,
+    pub last_block_number: u64,
+}
+
+impl Default for MockLightClient {
+    fn default() -> Self {
+        env::panic_str("Contract must be initialized")
+    }
+}
+
+#[near]
+impl MockLightClient {
+    #[init]
+    pub fn new(owner_id: AccountId) -> Self {
+        Self {
+            owner_id,
+            block_hashes: LookupMap::new(b"h"),
+            last_block_number: 0,
+        }
+    }
+
+    pub fn set_block_hash(&mut self, block_number: u64, block_hash: String) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let bytes = hex::decode(block_hash.trim_start_matches("0x")).expect("Invalid block hash");
+        let block_hash: [u8; 32] = bytes.try_into().unwrap_or_else(|_| env::panic_str("Block hash must be 32 bytes"));
+        self.block_hashes.insert(block_number, block_hash);
+        self.last_block_number = self.last_block_number.max(block_number);
+    }
+
+    /// Moves the reported chain head, e.g. to let a block reach finality.
+    pub fn set_last_block_number(&mut self, block_number: u64) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        self.last_block_number = block_number;
+    }
+
+    #[result_serializer(borsh)]
+    pub fn last_block_number(&self) -> u64 {
+        self.last_block_number
+    }
+
+    #[result_serializer(borsh)]
+    pub fn block_hash_safe(&self, #[serializer(borsh)] block_number: u64) -> Option<[u8; 32]> {
+        self.block_hashes.get(&block_number).copied()
+    }
+}
@@ -0,0 +1,283 @@
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::{self, json};
+use near_sdk::store::{IterableMap, LookupMap};
+use near_sdk::{env, near, require, AccountId, Gas, NearToken, Promise, PromiseOrValue};
+use xchain_core::events::NepXChainEvent;
+use xchain_core::{promise_succeeded, promise_value, BridgeRoute, CanonicalAssetId, ReceiptTokenMetadata};
+
+mod verification;
+
+pub use verification::ReceiptRecord;
+
+const GAS_XC_METADATA: Gas = Gas::from_tgas(5);
+const GAS_ON_TOKEN_METADATA: Gas = Gas::from_tgas(20);
+const GAS_STORAGE_DEPOSIT: Gas = Gas::from_tgas(10);
+const GAS_FT_TRANSFER: Gas = Gas::from_tgas(10);
+const GAS_ON_TRANSFER: Gas = Gas::from_tgas(10);
+const ONE_YOCTO: NearToken = NearToken::from_yoctonear(1);
+const STORAGE_DEPOSIT: NearToken = NearToken::from_yoctonear(1_250_000_000_000_000_000_000);
+const DEFAULT_PAGE_LIMIT: u32 = 100;
+
+#[near(contract_state)]
+pub struct XChainRegistry {
+    pub owner_id: AccountId,
+    pub asset_tokens: IterableMap<String, Vec<AccountId>>,
+    pub token_assets: LookupMap<AccountId, CanonicalAssetId>,
+    pub pools: LookupMap<AccountId, u128>,
+    /// The canonical asset key a receipt token's pool counts toward.
+    pub pool_assets: LookupMap<AccountId, String>,
+    /// Liquidity shares per provider and canonical asset key. Swaps are 1:1,
+    /// so the asset's pools always hold one unit per share and a provider can
+    /// withdraw from any of them.
+    pub shares: LookupMap<(AccountId, String), u128>,
+    pub bridges: IterableMap<AccountId, BridgeRoute>,
+    pub receipts: LookupMap<AccountId, ReceiptRecord>,
+}
+
+/// `msg` of an `ft_transfer_call` to the registry.
+#[near(serializers = [json])]
+pub enum TransferMessage {
+    AddLiquidity,
+    Swap { token_out: AccountId },
+}
+
+impl Default for XChainRegistry {
+    fn default() -> Self {
+        env::panic_str("Contract must be initialized")
+    }
+}
+
+#[near]
+impl XChainRegistry {
+    #[init]
+    pub fn new(owner_id: AccountId) -> Self {
+        Self {
+            owner_id,
+            asset_tokens: IterableMap::new(b"a"),
+            token_assets: LookupMap::new(b"t"),
+            pools: LookupMap::new(b"p"),
+            pool_assets: LookupMap::new(b"k"),
+            shares: LookupMap::new(b"s"),
+            bridges: IterableMap::new(b"b"),
+            receipts: LookupMap::new(b"r"),
+        }
+    }
+
+    /// Registers a receipt token under the canonical asset its `xc_metadata`
+    /// reports. The attached deposit pays for the registry's storage on the
+    /// token.
+    #[payable]
+    pub fn register_token(&mut self, token_id: AccountId) -> Promise {
+        require!(env::attached_deposit() >= STORAGE_DEPOSIT, "Attach storage deposit");
+        require!(!self.token_assets.contains_key(&token_id), "Token already registered");
+        Promise::new(token_id.clone())
+            .function_call(
+                "xc_metadata".to_string(),
+                b"{}".to_vec(),
+                NearToken::from_yoctonear(0),
+                GAS_XC_METADATA,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_ON_TOKEN_METADATA)
+                    .with_attached_deposit(env::attached_deposit())
+                    .on_token_metadata(token_id, env::predecessor_account_id()),
+            )
+    }
+
+    #[private]
+    #[payable]
+    pub fn on_token_metadata(&mut self, token_id: AccountId, caller: AccountId) -> bool {
+        let metadata = promise_value(0).and_then(|result| serde_json::from_slice::<ReceiptTokenMetadata>(&result).ok());
+        let metadata = metadata.filter(|m| m.nep141_token == token_id && !self.token_assets.contains_key(&token_id));
+        let Some(metadata) = metadata else {
+            let _ = Promise::new(caller).transfer(env::attached_deposit());
+            return false;
+        };
+
+        let canonical_asset = metadata.canonical_asset;
+        self.asset_tokens.entry(canonical_asset.key()).or_default().push(token_id.clone());
+        self.token_assets.insert(token_id.clone(), canonical_asset.clone());
+
+        NepXChainEvent::AssetRegistered {
+            canonical_asset,
+            receipt_token: token_id.clone(),
+        }.emit();
+
+        let _ = Promise::new(token_id).function_call(
+            "storage_deposit".to_string(),
+            json!({
+                "account_id": env::current_account_id(),
+                "registration_only": true
+            }).to_string().into_bytes(),
+            env::attached_deposit(),
+            GAS_STORAGE_DEPOSIT,
+        );
+        true
+    }
+
+    /// Redeems `amount` shares for `token_id`, which may be any pooled receipt
+    /// token of the canonical asset the shares were provided for. Withdrawals
+    /// do not require the token to still be verified, so providers can always
+    /// exit.
+    #[payable]
+    pub fn remove_liquidity(&mut self, token_id: AccountId, amount: U128) -> Promise {
+        near_sdk::assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let asset_key = self.pool_assets.get(&token_id).expect("No pool for token").clone();
+
+        let provided = self
+            .shares
+            .get_mut(&(account_id.clone(), asset_key))
+            .expect("No liquidity provided");
+        require!(*provided >= amount.0, "Amount exceeds provided liquidity");
+        *provided -= amount.0;
+
+        let pool = self.pools.get_mut(&token_id).expect("Insufficient liquidity");
+        require!(*pool >= amount.0, "Insufficient liquidity");
+        *pool -= amount.0;
+
+        NepXChainEvent::LiquidityRemoved {
+            account_id: account_id.clone(),
+            receipt_token: token_id.clone(),
+            amount,
+        }.emit();
+
+        Self::transfer_out(&token_id, &account_id, amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_ON_TRANSFER)
+                .on_remove_liquidity(token_id, account_id, amount),
+        )
+    }
+
+    #[private]
+    pub fn on_remove_liquidity(&mut self, token_id: AccountId, account_id: AccountId, amount: U128) -> bool {
+        if promise_succeeded(0) {
+            return true;
+        }
+        let asset_key = self.pool_assets.get(&token_id).expect("No pool for token").clone();
+        *self.shares.entry((account_id, asset_key)).or_insert(0) += amount.0;
+        *self.pools.entry(token_id).or_insert(0) += amount.0;
+        false
+    }
+
+    /// Resolves a swap: on a failed payout the pools are restored and the
+    /// input amount is reported unused so `token_in` refunds the sender.
+    #[private]
+    pub fn on_swap_transfer(&mut self, token_in: AccountId, token_out: AccountId, amount: U128) -> U128 {
+        if promise_succeeded(0) {
+            return U128(0);
+        }
+        *self.pools.entry(token_in).or_insert(0) -= amount.0;
+        *self.pools.entry(token_out).or_insert(0) += amount.0;
+        amount
+    }
+
+    /// Receipt tokens registered for `canonical_asset` that were verified
+    /// against it. Tokens only listed by their own `xc_metadata` are left out.
+    pub fn get_receipt_tokens(&self, canonical_asset: CanonicalAssetId) -> Vec<AccountId> {
+        let key = canonical_asset.key();
+        self.asset_tokens
+            .get(&key)
+            .map(|tokens| {
+                tokens
+                    .iter()
+                    .filter(|token_id| self.verified_key_of(token_id).as_ref() == Some(&key))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get_token_asset(&self, token_id: AccountId) -> Option<CanonicalAssetId> {
+        self.token_assets.get(&token_id).cloned()
+    }
+
+    pub fn get_pool(&self, token_id: AccountId) -> U128 {
+        U128(self.pools.get(&token_id).copied().unwrap_or(0))
+    }
+
+    /// The shares `account_id` holds in the pools of `canonical_asset`.
+    pub fn get_liquidity(&self, account_id: AccountId, canonical_asset: CanonicalAssetId) -> U128 {
+        U128(self.shares.get(&(account_id, canonical_asset.key())).copied().unwrap_or(0))
+    }
+}
+
+impl XChainRegistry {
+    /// The key of the canonical asset `token_id` was verified against, if it
+    /// is currently verified.
+    fn verified_key_of(&self, token_id: &AccountId) -> Option<String> {
+        if !self.is_verified_receipt(token_id.clone()) {
+            return None;
+        }
+        self.receipts.get(token_id)?.canonical_asset.as_ref().map(CanonicalAssetId::key)
+    }
+
+    /// The key of the canonical asset `token_id` was verified against. The
+    /// token's pool must not count toward another asset.
+    fn verified_asset_key(&mut self, token_id: &AccountId) -> String {
+        let key = self.verified_key_of(token_id).unwrap_or_else(|| env::panic_str("Receipt token not verified"));
+        let pool_key = self.pool_assets.entry(token_id.clone()).or_insert_with(|| key.clone());
+        require!(*pool_key == key, "Pool holds another asset");
+        key
+    }
+
+    fn transfer_out(token_id: &AccountId, receiver_id: &AccountId, amount: U128) -> Promise {
+        Promise::new(token_id.clone()).function_call(
+            "ft_transfer".to_string(),
+            json!({
+                "receiver_id": receiver_id,
+                "amount": amount
+            }).to_string().into_bytes(),
+            ONE_YOCTO,
+            GAS_FT_TRANSFER,
+        )
+    }
+}
+
+#[near]
+impl FungibleTokenReceiver for XChainRegistry {
+    /// Verified receipt tokens sent here either join the pool
+    /// (`"AddLiquidity"`) or are swapped 1:1 for another verified receipt
+    /// token of the same canonical asset (`{"Swap":{"token_out":"..."}}`).
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token_in = env::predecessor_account_id();
+        let asset_key = self.verified_asset_key(&token_in);
+        let message: TransferMessage = serde_json::from_str(&msg).expect("Invalid transfer message");
+
+        match message {
+            TransferMessage::AddLiquidity => {
+                *self.pools.entry(token_in.clone()).or_insert(0) += amount.0;
+                *self.shares.entry((sender_id.clone(), asset_key)).or_insert(0) += amount.0;
+                NepXChainEvent::LiquidityAdded {
+                    account_id: sender_id,
+                    receipt_token: token_in,
+                    amount,
+                }.emit();
+                PromiseOrValue::Value(U128(0))
+            }
+            TransferMessage::Swap { token_out } => {
+                require!(token_out != token_in, "Cannot swap a token for itself");
+                require!(self.verified_asset_key(&token_out) == asset_key, "Tokens represent different assets");
+                let pool_out = self.pools.get_mut(&token_out).expect("Insufficient liquidity");
+                require!(*pool_out >= amount.0, "Insufficient liquidity");
+                *pool_out -= amount.0;
+                *self.pools.entry(token_in.clone()).or_insert(0) += amount.0;
+
+                NepXChainEvent::ReceiptTokenSwapped {
+                    account_id: sender_id.clone(),
+                    token_in: token_in.clone(),
+                    token_out: token_out.clone(),
+                    amount,
+                }.emit();
+
+                PromiseOrValue::Promise(Self::transfer_out(&token_out, &sender_id, amount).then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_ON_TRANSFER)
+                        .on_swap_transfer(token_in, token_out, amount),
+                ))
+            }
+        }
+    }
+}
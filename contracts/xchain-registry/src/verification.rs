@@ -0,0 +1,164 @@
+use near_sdk::serde_json::{self, json};
+use near_sdk::{env, near, require, AccountId, Gas, NearToken, Promise, Timestamp};
+use xchain_core::events::NepXChainEvent;
+use xchain_core::{promise_value, BridgeRoute, CanonicalAssetId, ReceiptTokenMetadata};
+
+use crate::{XChainRegistry, XChainRegistryExt, DEFAULT_PAGE_LIMIT};
+
+const GAS_VIEW: Gas = Gas::from_tgas(5);
+const GAS_ON_BRIDGE_ROUTE: Gas = Gas::from_tgas(10);
+const GAS_ON_VERIFY_RECEIPT: Gas = Gas::from_tgas(10);
+
+/// A receipt token registered by a bridge. `verified` holds the outcome of
+/// the last cross-check against the token and the bridge.
+#[near(serializers = [json, borsh])]
+#[derive(Clone, Debug)]
+pub struct ReceiptRecord {
+    pub bridge_contract: AccountId,
+    pub canonical_asset: Option<CanonicalAssetId>,
+    pub verified: bool,
+    pub verified_at: Timestamp,
+}
+
+#[near]
+impl XChainRegistry {
+    /// Registers a bridge. The bridge must implement `XChainCore` and report
+    /// itself as its route's `bridge_contract`.
+    pub fn register_bridge(&mut self, bridge_contract: AccountId) -> Promise {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        Promise::new(bridge_contract.clone())
+            .function_call("get_bridge_route".to_string(), b"{}".to_vec(), NearToken::from_yoctonear(0), GAS_VIEW)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_ON_BRIDGE_ROUTE)
+                    .on_bridge_route(bridge_contract),
+            )
+    }
+
+    #[private]
+    pub fn on_bridge_route(&mut self, bridge_contract: AccountId) -> bool {
+        let route = promise_value(0).and_then(|result| serde_json::from_slice::<BridgeRoute>(&result).ok());
+        let Some(route) = route.filter(|route| route.bridge_contract == bridge_contract) else {
+            return false;
+        };
+        NepXChainEvent::BridgeRegistered {
+            bridge_contract: bridge_contract.clone(),
+            security_model: route.security_model.clone(),
+        }.emit();
+        self.bridges.insert(bridge_contract, route);
+        true
+    }
+
+    pub fn deregister_bridge(&mut self, bridge_contract: AccountId) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        require!(self.bridges.remove(&bridge_contract).is_some(), "Bridge not registered");
+        NepXChainEvent::BridgeDeregistered {
+            caller: env::predecessor_account_id(),
+            bridge_contract,
+        }.emit();
+    }
+
+    /// Called by a registered bridge for a receipt token it serves. The token
+    /// is verified right away; `verify_receipt` repeats the check later. A
+    /// token registered by another bridge can only be taken over once that
+    /// bridge has been deregistered.
+    pub fn register_receipt(&mut self, token_id: AccountId) -> Promise {
+        let bridge_contract = env::predecessor_account_id();
+        require!(self.bridges.contains_key(&bridge_contract), "Bridge not registered");
+        if let Some(record) = self.receipts.get(&token_id) {
+            require!(
+                record.bridge_contract == bridge_contract || !self.bridges.contains_key(&record.bridge_contract),
+                "Receipt registered by another bridge"
+            );
+        }
+        self.receipts.insert(token_id.clone(), ReceiptRecord {
+            bridge_contract,
+            canonical_asset: None,
+            verified: false,
+            verified_at: env::block_timestamp(),
+        });
+        self.verify_receipt(token_id)
+    }
+
+    /// Cross-checks a registered receipt: the token's `xc_metadata` must list
+    /// the registering bridge among its routes, and that bridge's
+    /// `get_canonical_asset` must agree with the token's canonical asset.
+    pub fn verify_receipt(&mut self, token_id: AccountId) -> Promise {
+        let record = self.receipts.get(&token_id).expect("Receipt not registered");
+        let metadata = Promise::new(token_id.clone()).function_call(
+            "xc_metadata".to_string(),
+            b"{}".to_vec(),
+            NearToken::from_yoctonear(0),
+            GAS_VIEW,
+        );
+        let canonical_asset = Promise::new(record.bridge_contract.clone()).function_call(
+            "get_canonical_asset".to_string(),
+            json!({ "token_id": token_id }).to_string().into_bytes(),
+            NearToken::from_yoctonear(0),
+            GAS_VIEW,
+        );
+        metadata.and(canonical_asset).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_ON_VERIFY_RECEIPT)
+                .on_verify_receipt(token_id),
+        )
+    }
+
+    #[private]
+    pub fn on_verify_receipt(&mut self, token_id: AccountId) -> bool {
+        let metadata = promise_value(0).and_then(|result| serde_json::from_slice::<ReceiptTokenMetadata>(&result).ok());
+        let bridge_asset = promise_value(1)
+            .and_then(|result| serde_json::from_slice::<Option<CanonicalAssetId>>(&result).ok())
+            .flatten();
+        let record = self.receipts.get_mut(&token_id).expect("Receipt not registered");
+        let canonical_asset = match (metadata, bridge_asset) {
+            (Some(metadata), Some(bridge_asset))
+                if metadata.nep141_token == token_id
+                    && metadata.canonical_asset.key() == bridge_asset.key()
+                    && (metadata.bridge_route.bridge_contract == record.bridge_contract
+                        || metadata
+                            .bridge_routes
+                            .iter()
+                            .any(|route| route.route.bridge_contract == record.bridge_contract)) =>
+            {
+                Some(bridge_asset)
+            }
+            _ => None,
+        };
+        let verified = canonical_asset.is_some();
+        record.canonical_asset = canonical_asset;
+        record.verified = verified;
+        record.verified_at = env::block_timestamp();
+        NepXChainEvent::ReceiptVerified {
+            receipt_token: token_id,
+            bridge_contract: record.bridge_contract.clone(),
+            verified,
+        }.emit();
+        verified
+    }
+
+    /// True when `token_id` passed its last verification and the bridge that
+    /// registered it is still registered.
+    pub fn is_verified_receipt(&self, token_id: AccountId) -> bool {
+        self.receipts
+            .get(&token_id)
+            .is_some_and(|record| record.verified && self.bridges.contains_key(&record.bridge_contract))
+    }
+
+    pub fn get_receipt_record(&self, token_id: AccountId) -> Option<ReceiptRecord> {
+        self.receipts.get(&token_id).cloned()
+    }
+
+    pub fn get_bridge(&self, bridge_contract: AccountId) -> Option<BridgeRoute> {
+        self.bridges.get(&bridge_contract).cloned()
+    }
+
+    pub fn get_bridges(&self, from_index: Option<u32>, limit: Option<u32>) -> Vec<BridgeRoute> {
+        self.bridges
+            .values()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
+}
@@ -1,11 +1,15 @@
 use near_sdk::{near, env, require, AccountId, PromiseOrValue};
 use near_sdk::json_types::U128;
+use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
 use near_contract_standards::fungible_token::FungibleToken;
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider,
 };
 use near_contract_standards::storage_management::StorageManagement;
-use xchain_core::{ReceiptTokenMetadata, XChainReceiptToken, ChainId};
+use xchain_core::{AuthorizedRoute, BridgeRoute, ReceiptTokenMetadata, XChainReceiptToken, ChainId};
+
+mod migration;
 
 #[near(contract_state)]
 pub struct ReceiptToken {
@@ -47,39 +51,90 @@ impl ReceiptToken {
     }
 
     pub fn mint(&mut self, account_id: AccountId, amount: U128) {
-        require!(env::predecessor_account_id() == self.owner_id, "Only bridge can mint");
+        let route = self.caller_route().expect("Only bridge can mint");
+        route.supply = U128(route.supply.0 + amount.0);
+        self.xc_metadata.total_locked = U128(self.xc_metadata.total_locked.0 + amount.0);
         self.token.internal_deposit(&account_id, amount.0);
-        
-        env::log_str(&format!(
-            "EVENT_JSON:{}",
-            near_sdk::serde_json::json!({
-                "standard": "nep141",
-                "version": "1.0.0",
-                "event": "ft_mint",
-                "data": [{
-                    "owner_id": account_id,
-                    "amount": amount
-                }]
-            })
-        ));
+
+        FtMint {
+            owner_id: &account_id,
+            amount,
+            memo: None,
+        }
+        .emit();
+    }
+
+    pub fn xc_add_route(&mut self, bridge_route: BridgeRoute, destination_chains: Vec<ChainId>) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        require!(
+            self.route_index(&bridge_route.bridge_contract).is_none(),
+            "Route already authorized"
+        );
+        self.xc_metadata.bridge_routes.push(AuthorizedRoute {
+            route: bridge_route,
+            destination_chains,
+            supply: U128(0),
+        });
+    }
+
+    pub fn xc_remove_route(&mut self, bridge_contract: AccountId) {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let index = self.route_index(&bridge_contract).expect("Route not found");
+        require!(self.xc_metadata.bridge_routes[index].supply.0 == 0, "Route still has supply");
+        self.xc_metadata.bridge_routes.remove(index);
+    }
+
+    /// Lets a route's bridge publish its current security model and
+    /// destination chains.
+    pub fn xc_update_route(&mut self, bridge_route: BridgeRoute, destination_chains: Vec<ChainId>) {
+        require!(
+            bridge_route.bridge_contract == env::predecessor_account_id(),
+            "Route must belong to the caller"
+        );
+        let route = self.caller_route().expect("Route not found");
+        route.route = bridge_route.clone();
+        route.destination_chains = destination_chains;
+        if bridge_route.bridge_contract == self.owner_id {
+            self.xc_metadata.bridge_route = bridge_route;
+        }
+    }
+
+    /// Updates the deploying bridge's route, keeping its destination chains.
+    pub fn xc_set_bridge_route(&mut self, bridge_route: BridgeRoute) {
+        require!(env::predecessor_account_id() == self.owner_id, "Only bridge can update route");
+        require!(bridge_route.bridge_contract == self.owner_id, "Route must belong to the bridge");
+        let route = self.caller_route().expect("Route not found");
+        route.route = bridge_route.clone();
+        self.xc_metadata.bridge_route = bridge_route;
     }
 
     pub fn burn(&mut self, account_id: AccountId, amount: U128) {
-        require!(env::predecessor_account_id() == self.owner_id, "Only bridge can burn");
+        let route = self.caller_route().expect("Only bridge can burn");
+        require!(route.supply.0 >= amount.0, "Amount exceeds route supply");
+        route.supply = U128(route.supply.0 - amount.0);
+        self.xc_metadata.total_locked = U128(self.xc_metadata.total_locked.0 - amount.0);
         self.token.internal_withdraw(&account_id, amount.0);
-        
-        env::log_str(&format!(
-            "EVENT_JSON:{}",
-            near_sdk::serde_json::json!({
-                "standard": "nep141",
-                "version": "1.0.0",
-                "event": "ft_burn",
-                "data": [{
-                    "owner_id": account_id,
-                    "amount": amount
-                }]
-            })
-        ));
+
+        FtBurn {
+            owner_id: &account_id,
+            amount,
+            memo: None,
+        }
+        .emit();
+    }
+}
+
+impl ReceiptToken {
+    fn route_index(&self, bridge_contract: &AccountId) -> Option<usize> {
+        self.xc_metadata
+            .bridge_routes
+            .iter()
+            .position(|r| &r.route.bridge_contract == bridge_contract)
+    }
+
+    fn caller_route(&mut self) -> Option<&mut AuthorizedRoute> {
+        let index = self.route_index(&env::predecessor_account_id())?;
+        self.xc_metadata.bridge_routes.get_mut(index)
     }
 }
 
@@ -110,6 +165,21 @@ impl near_contract_standards::fungible_token::core::FungibleTokenCore for Receip
     }
 }
 
+#[near]
+impl FungibleTokenResolver for ReceiptToken {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let (used_amount, _burned_amount) =
+            self.token.internal_ft_resolve_transfer(&sender_id, receiver_id, amount);
+        used_amount.into()
+    }
+}
+
 #[near]
 impl FungibleTokenMetadataProvider for ReceiptToken {
     fn ft_metadata(&self) -> FungibleTokenMetadata {
@@ -124,7 +194,10 @@ impl XChainReceiptToken for ReceiptToken {
     }
 
     fn xc_is_bridgeable_to(&self, chain_id: ChainId) -> bool {
-        self.xc_metadata.canonical_asset.source_chain_id == chain_id
+        self.xc_metadata
+            .bridge_routes
+            .iter()
+            .any(|r| r.destination_chains.contains(&chain_id))
     }
 
     fn xc_total_locked(&self) -> U128 {
@@ -0,0 +1,71 @@
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::json_types::U128;
+use near_sdk::{env, near, require, AccountId, Gas, NearToken, Promise};
+use xchain_core::{AuthorizedRoute, BridgeRoute, CanonicalAssetId, ReceiptTokenMetadata};
+
+use crate::{ReceiptToken, ReceiptTokenExt};
+
+const GAS_MIGRATE: Gas = Gas::from_tgas(50);
+
+/// The originally deployed layout, with a single bridge route.
+#[near(serializers = [borsh])]
+struct ReceiptTokenV1 {
+    token: FungibleToken,
+    metadata: FungibleTokenMetadata,
+    xc_metadata: ReceiptTokenMetadataV1,
+    owner_id: AccountId,
+}
+
+#[near(serializers = [borsh])]
+struct ReceiptTokenMetadataV1 {
+    canonical_asset: CanonicalAssetId,
+    bridge_route: BridgeRoute,
+    total_locked: U128,
+    nep141_token: AccountId,
+}
+
+#[near]
+impl ReceiptToken {
+    /// Deploys the contract code passed as the raw call input and runs its
+    /// `migrate`. Only the owner, i.e. the deploying bridge, can upgrade.
+    pub fn upgrade(&self) -> Promise {
+        require!(env::predecessor_account_id() == self.owner_id, "Unauthorized");
+        let code = env::input().expect("Missing contract code");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call("migrate".to_string(), vec![], NearToken::from_yoctonear(0), GAS_MIGRATE)
+    }
+
+    /// Upgrades from the originally deployed layout. The single route becomes
+    /// the only authorized route, bridgeable to the asset's source chain as
+    /// before. The original token never counted `total_locked`, so the route's
+    /// supply and the total start from the token's total supply. State already
+    /// in the current layout is kept as is.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("No state to migrate");
+        if let Ok(current) = near_sdk::borsh::from_slice::<Self>(&state) {
+            return current;
+        }
+        let old: ReceiptTokenV1 = near_sdk::borsh::from_slice(&state).expect("Cannot deserialize token state");
+        let supply = U128(old.token.total_supply);
+        Self {
+            metadata: old.metadata,
+            xc_metadata: ReceiptTokenMetadata {
+                bridge_routes: vec![AuthorizedRoute {
+                    route: old.xc_metadata.bridge_route.clone(),
+                    destination_chains: vec![old.xc_metadata.canonical_asset.source_chain_id.clone()],
+                    supply,
+                }],
+                bridge_route: old.xc_metadata.bridge_route,
+                canonical_asset: old.xc_metadata.canonical_asset,
+                total_locked: supply,
+                nep141_token: old.xc_metadata.nep141_token,
+            },
+            token: old.token,
+            owner_id: old.owner_id,
+        }
+    }
+}
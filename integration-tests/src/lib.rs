@@ -1,71 +1,123 @@
+use ed25519_dalek::{Signer, SigningKey};
+use near_workspaces::network::Sandbox;
 use near_workspaces::types::NearToken;
+use near_workspaces::{Account, Contract, Worker};
 use serde_json::json;
-use xchain_core::{AssetStandard, BridgePayload, CanonicalAssetId};
-use near_sdk::json_types::U128;
-use near_sdk::borsh::BorshSerialize;
+use xchain_core::{AssetStandard, BridgePayload, CanonicalAssetId, MessageOrdering, MessageReceipt, TokenCallPayload, XChainMessage};
+use xchain_core::events::NepXChainEvent;
+use near_sdk::json_types::{Base58CryptoHash, U128};
 
-#[tokio::test]
-async fn test_full_bridge_flow() -> anyhow::Result<()> {
-    let worker = near_workspaces::sandbox().await?;
-    let owner = worker.root_account()?;
+struct BridgeTestEnv {
+    worker: Worker<Sandbox>,
+    owner: Account,
+    bridge: Contract,
+    user: Account,
+}
+
+fn validator_key() -> SigningKey {
+    SigningKey::from_bytes(&[7u8; 32])
+}
+
+fn validator_public_key() -> String {
+    format!("ed25519:{}", bs58::encode(validator_key().verifying_key().as_bytes()).into_string())
+}
+
+/// `signer`'s signature over the committee digest of `message` in `epoch`,
+/// bound to the verifying contract `verifier`.
+fn validator_signature(signer: &SigningKey, verifier: &near_workspaces::AccountId, epoch: u64, message: &[u8]) -> Vec<u8> {
+    use sha2::{Digest, Sha256};
+    let mut data = b"nep_xchain_committee".to_vec();
+    data.extend(near_sdk::borsh::to_vec(&verifier.to_string()).unwrap());
+    data.extend_from_slice(&epoch.to_le_bytes());
+    data.extend_from_slice(message);
+    signer.sign(&Sha256::digest(&data)).to_bytes().to_vec()
+}
+
+/// A `CommitteeProof` for `message` signed by the test validator in `epoch`.
+fn committee_proof(verifier: &near_workspaces::AccountId, epoch: u64, message: &[u8]) -> serde_json::Value {
+    json!({
+        "epoch": epoch,
+        "signatures": [[validator_public_key(), validator_signature(&validator_key(), verifier, epoch, message)]]
+    })
+}
 
-    println!("Compiling and deploying bridge...");
+async fn deploy_bridge(worker: &Worker<Sandbox>, owner: &Account) -> anyhow::Result<Contract> {
     let bridge_wasm = near_workspaces::compile_project("./contracts/xchain-bridge").await?;
     let bridge = worker.dev_deploy(&bridge_wasm).await?;
-    
-    println!("Initializing bridge...");
-    let init_result = bridge.call("new")
+    bridge.call("new")
         .args_json(json!({
             "owner_id": owner.id(),
-            "initial_validators": []
+            "initial_validators": [validator_public_key()],
+            "chain_id": "near:testnet"
         }))
         .transact()
-        .await?;
-    assert!(init_result.is_success(), "Bridge init failed");
+        .await?
+        .into_result()?;
+    Ok(bridge)
+}
+
+/// An initialized bridge with the receipt token code set, `eip155:1`
+/// enabled and a funded `alice` account.
+async fn setup_bridge() -> anyhow::Result<BridgeTestEnv> {
+    let worker = near_workspaces::sandbox().await?;
+    let owner = worker.root_account()?;
+    let bridge = deploy_bridge(&worker, &owner).await?;
 
-    println!("Compiling token...");
     let token_wasm = near_workspaces::compile_project("./contracts/xchain-token").await?;
-    
-    println!("Setting receipt token code...");
-    let set_code_result = bridge.call("set_receipt_token_code")
-        .args_borsh(token_wasm)
+    owner.call(bridge.id(), "set_receipt_token_code")
+        .args_json(json!({"code": token_wasm}))
+        .max_gas()
         .transact()
-        .await?;
-    assert!(set_code_result.is_success(), "Set code failed");
+        .await?
+        .into_result()?;
+
+    owner.call(bridge.id(), "set_supported_chain")
+        .args_json(json!({
+            "chain_id": "eip155:1",
+            "config": {"enabled": true, "address_format": "Evm", "finality_blocks": 64}
+        }))
+        .transact()
+        .await?
+        .into_result()?;
 
-    println!("Creating test user...");
     let user = owner.create_subaccount("alice")
         .initial_balance(NearToken::from_near(10))
         .transact()
         .await?
         .into_result()?;
 
-    println!("Preparing bridge payload...");
+    Ok(BridgeTestEnv { worker, owner, bridge, user })
+}
+
+#[tokio::test]
+async fn test_full_bridge_flow() -> anyhow::Result<()> {
+    let BridgeTestEnv { worker, bridge, user, .. } = setup_bridge().await?;
+
     let canonical_asset = CanonicalAssetId {
-        source_chain_id: "ethereum:1".to_string(),
+        source_chain_id: "eip155:1".parse().unwrap(),
         source_contract: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
         asset_standard: AssetStandard::ERC20,
     };
     
     let payload = BridgePayload {
         nonce: U128(1),
-        source_chain: "ethereum:1".to_string(),
-        target_chain: "near:testnet".to_string(),
+        source_chain: "eip155:1".parse().unwrap(),
+        target_chain: "near:testnet".parse().unwrap(),
         asset: canonical_asset,
         amount: U128(1_000_000_000),
         receiver: user.id().to_string().parse().unwrap(),
         source_tx_hash: "0xabc123def456".to_string(),
     };
     
-    let proof_data = payload.try_to_vec().expect("Failed to serialize payload");
+    let proof_data = near_sdk::borsh::to_vec(&payload).expect("Failed to serialize payload");
 
-    println!("Executing bridge_in...");
     let bridge_in_result = bridge.call("bridge_in")
         .args_json(json!({
             "proof": {
                 "source_tx_hash": "0xabc123def456",
                 "proof_data": proof_data,
-                "block_height": 18500000
+                "block_height": 18500000,
+                "committee_proof": committee_proof(bridge.id(), 0, &proof_data)
             },
             "receiver_id": user.id()
         }))
@@ -73,81 +125,1765 @@ async fn test_full_bridge_flow() -> anyhow::Result<()> {
         .max_gas()
         .transact()
         .await?;
-    
-    println!("Bridge in result: {:?}", bridge_in_result.logs());
-    
+
     let has_bridge_event = bridge_in_result.logs().iter()
-        .any(|log| log.contains("nep_xchain") && log.contains("bridge_in"));
+        .filter_map(|log| NepXChainEvent::from_log(log))
+        .any(|event| matches!(
+            event,
+            NepXChainEvent::BridgeIn { amount, ref receiver_id, .. }
+                if amount.0 == 1_000_000_000 && receiver_id.as_str() == user.id().as_str()
+        ));
     assert!(has_bridge_event, "Expected bridge_in event not found");
 
-    println!("Test passed!");
+    let processed: bool = bridge.view("is_transfer_processed")
+        .args_json(json!({"source_chain": "eip155:1", "nonce": "1"}))
+        .await?
+        .json()?;
+    assert!(processed);
+
+    // Nonces start at 1, so the first transfer only moves the watermark.
+    let usage: serde_json::Value = bridge.view("get_replay_storage_usage").args_json(json!({})).await?.json()?;
+    assert_eq!(usage["chains"], 1);
+    assert_eq!(usage["bitmap_words"], 0);
+
+    // Same transfer under a different proof envelope must be rejected.
+    let replay_result = bridge.call("bridge_in")
+        .args_json(json!({
+            "proof": {
+                "source_tx_hash": "0xabc123def456",
+                "proof_data": proof_data,
+                "block_height": 18500001,
+                "committee_proof": committee_proof(bridge.id(), 0, &proof_data)
+            },
+            "receiver_id": user.id()
+        }))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(replay_result.is_failure(), "Replayed transfer was accepted");
+
+    let receipt_token = bridge_in_result.logs().iter()
+        .filter_map(|log| NepXChainEvent::from_log(log))
+        .find_map(|event| match event {
+            NepXChainEvent::BridgeIn { receipt_token, .. } => Some(receipt_token),
+            _ => None,
+        })
+        .unwrap();
+
+    // Last character's case flipped: fails the EIP-55 checksum.
+    let bridge_out_result = user.call(bridge.id(), "bridge_out")
+        .args_json(json!({
+            "token_id": receipt_token,
+            "amount": "1000",
+            "destination_chain": "eip155:1",
+            "destination_address": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"
+        }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact()
+        .await?;
+    let failure = format!("{:?}", bridge_out_result.into_result().unwrap_err());
+    assert!(failure.contains("Invalid EIP-55 checksum"), "{}", failure);
+
+    // A lowercased spelling of the same contract must reuse the receipt token.
+    let lowercase_payload = BridgePayload {
+        nonce: U128(2),
+        source_tx_hash: "0xabc123def457".to_string(),
+        asset: CanonicalAssetId {
+            source_contract: payload.asset.source_contract.to_lowercase(),
+            ..payload.asset.clone()
+        },
+        ..payload.clone()
+    };
+    let lowercase_proof_data = near_sdk::borsh::to_vec(&lowercase_payload).expect("Failed to serialize payload");
+    let lowercase_result = bridge.call("bridge_in")
+        .args_json(json!({
+            "proof": {
+                "source_tx_hash": "0xabc123def457",
+                "proof_data": lowercase_proof_data,
+                "block_height": 18500002,
+                "committee_proof": committee_proof(bridge.id(), 0, &lowercase_proof_data)
+            },
+            "receiver_id": user.id()
+        }))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?;
+    let second_receipt_token = lowercase_result.logs().iter()
+        .filter_map(|log| NepXChainEvent::from_log(log))
+        .find_map(|event| match event {
+            NepXChainEvent::BridgeIn { receipt_token, .. } => Some(receipt_token),
+            _ => None,
+        })
+        .expect("Expected bridge_in event not found");
+    assert_eq!(second_receipt_token, receipt_token);
+
+    // An asset claimed to originate on a chain other than the transfer's.
+    let foreign_payload = BridgePayload {
+        nonce: U128(3),
+        source_tx_hash: "0xabc123def458".to_string(),
+        asset: CanonicalAssetId {
+            source_chain_id: "eip155:137".parse().unwrap(),
+            ..payload.asset.clone()
+        },
+        ..payload.clone()
+    };
+    let foreign_proof_data = near_sdk::borsh::to_vec(&foreign_payload).expect("Failed to serialize payload");
+    let foreign_result = bridge.call("bridge_in")
+        .args_json(json!({
+            "proof": {
+                "source_tx_hash": "0xabc123def458",
+                "proof_data": foreign_proof_data,
+                "block_height": 18500003,
+                "committee_proof": committee_proof(bridge.id(), 0, &foreign_proof_data)
+            },
+            "receiver_id": user.id()
+        }))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?;
+    let failure = format!("{:?}", foreign_result.into_result().unwrap_err());
+    assert!(failure.contains("Asset source chain mismatch"), "{}", failure);
+
+    let caip19_token: Option<String> = bridge.view("get_receipt_token_by_caip19")
+        .args_json(json!({"asset": "eip155:1/erc20:0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"}))
+        .await?
+        .json()?;
+    assert_eq!(caip19_token.as_deref(), Some(receipt_token.as_str()));
+
+    let count: u32 = bridge.view("bridged_asset_count").await?.json()?;
+    assert_eq!(count, 1);
+    let assets: serde_json::Value = bridge.view("list_bridged_assets")
+        .args_json(json!({"from_index": 0, "limit": 10}))
+        .await?
+        .json()?;
+    assert_eq!(assets[0]["receipt_token"], receipt_token.as_str());
+    assert_eq!(assets[0]["caip19"], "eip155:1/erc20:0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    // Both spellings were locked into the same token.
+    assert_eq!(assets[0]["total_locked"], "2000000000");
+
+    let route: serde_json::Value = bridge.view("get_bridge_route").await?.json()?;
+    assert_eq!(assets[0]["bridge_route"], route);
+    let history: serde_json::Value = bridge.view("get_route_history").args_json(json!({})).await?.json()?;
+    assert_eq!(history.as_array().map(Vec::len), Some(1));
+
+    user.call(bridge.id(), "refresh_bridge_route")
+        .args_json(json!({"token_id": receipt_token}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let xc_metadata: serde_json::Value = worker.view(&receipt_token.as_str().parse()?, "xc_metadata").await?.json()?;
+    assert_eq!(xc_metadata["bridge_route"], route);
+    assert_eq!(xc_metadata["bridge_routes"][0]["route"], route);
+    assert_eq!(xc_metadata["bridge_routes"][0]["destination_chains"], json!(["eip155:1"]));
+    let bridgeable: bool = worker.view(&receipt_token.as_str().parse()?, "xc_is_bridgeable_to")
+        .args_json(json!({"chain_id": "eip155:1"}))
+        .await?
+        .json()?;
+    assert!(bridgeable);
+
     Ok(())
 }
 
 #[tokio::test]
-async fn test_bridge_pause() -> anyhow::Result<()> {
-    let worker = near_workspaces::sandbox().await?;
-    let owner = worker.root_account()?;
+async fn test_bridge_out_charges_fee_after_burn() -> anyhow::Result<()> {
+    let BridgeTestEnv { worker, owner, bridge, user } = setup_bridge().await?;
+    let treasury = owner.create_subaccount("treasury").transact().await?.into_result()?;
+    owner.call(bridge.id(), "set_treasury")
+        .args_json(json!({"treasury": treasury.id()}))
+        .transact()
+        .await?
+        .into_result()?;
 
-    let bridge_wasm = near_workspaces::compile_project("./contracts/xchain-bridge").await?;
-    let bridge = worker.dev_deploy(&bridge_wasm).await?;
-    
-    bridge.call("new")
+    let payload = usdc_payload(user.id(), 1, 1_000_000_000, "0xout1");
+    let receipt_token = bridge_in_payload(&bridge, &payload).await?.into_result()?.logs().into_iter()
+        .filter_map(NepXChainEvent::from_log)
+        .find_map(|event| match event {
+            NepXChainEvent::BridgeIn { receipt_token, .. } => Some(receipt_token),
+            _ => None,
+        })
+        .expect("Expected bridge_in event not found");
+    let token_id: near_workspaces::AccountId = receipt_token.as_str().parse()?;
+
+    // alice was not registered with the new token, so she claims after registering.
+    user.call(&token_id, "storage_deposit")
+        .args_json(json!({}))
+        .deposit(NearToken::from_millinear(125))
+        .transact()
+        .await?
+        .into_result()?;
+    user.call(bridge.id(), "claim")
+        .args_json(json!({"canonical_asset": payload.asset}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let bridge_out = |amount: &str| user.call(bridge.id(), "bridge_out")
         .args_json(json!({
-            "owner_id": owner.id(),
-            "initial_validators": []
+            "token_id": token_id,
+            "amount": amount,
+            "destination_chain": "eip155:1",
+            "destination_address": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
         }))
+        .deposit(NearToken::from_millinear(100))
+        .max_gas()
+        .transact();
+    let bridged_out = |logs: Vec<&str>| logs.into_iter()
+        .filter_map(NepXChainEvent::from_log)
+        .any(|event| matches!(event, NepXChainEvent::BridgeOut { .. }));
+
+    // More than alice holds: the burn fails, nothing is emitted and the fee
+    // does not reach the treasury.
+    let treasury_before = treasury.view_account().await?.balance;
+    let failed = bridge_out("5000000000").await?;
+    assert!(!bridged_out(failed.logs()), "BridgeOut emitted for a failed burn");
+    assert_eq!(treasury.view_account().await?.balance, treasury_before);
+
+    let succeeded = bridge_out("400000000").await?.into_result()?;
+    assert!(bridged_out(succeeded.logs()), "Expected bridge_out event not found");
+    let treasury_after = treasury.view_account().await?.balance;
+    assert_eq!(
+        treasury_after.as_yoctonear() - treasury_before.as_yoctonear(),
+        NearToken::from_millinear(100).as_yoctonear()
+    );
+    let balance: U128 = worker.view(&token_id, "ft_balance_of")
+        .args_json(json!({"account_id": user.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance.0, 600_000_000);
+    let assets: serde_json::Value = bridge.view("list_bridged_assets").args_json(json!({})).await?.json()?;
+    assert_eq!(assets[0]["total_locked"], "600000000");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upgrade_receipt_token() -> anyhow::Result<()> {
+    let BridgeTestEnv { worker, owner, bridge, user } = setup_bridge().await?;
+    let receipt_token = bridge_in_payload(&bridge, &usdc_payload(user.id(), 1, 1_000, "0xupgrade")).await?
+        .into_result()?
+        .logs()
+        .into_iter()
+        .filter_map(NepXChainEvent::from_log)
+        .find_map(|event| match event {
+            NepXChainEvent::BridgeIn { receipt_token, .. } => Some(receipt_token),
+            _ => None,
+        })
+        .expect("Expected bridge_in event not found");
+    let token_id: near_workspaces::AccountId = receipt_token.as_str().parse()?;
+    let xc_metadata: serde_json::Value = worker.view(&token_id, "xc_metadata").await?.json()?;
+
+    let result = user.call(bridge.id(), "upgrade_receipt_token")
+        .args_json(json!({"token_id": token_id}))
+        .max_gas()
         .transact()
         .await?;
+    assert!(result.is_failure(), "Only the owner can upgrade receipt tokens");
+    let result = user.call(&token_id, "upgrade")
+        .args(b"code".to_vec())
+        .max_gas()
+        .transact()
+        .await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("Unauthorized"), "{}", failure);
+
+    let upgrade = owner.call(bridge.id(), "upgrade_receipt_token")
+        .args_json(json!({"token_id": token_id}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let upgraded = upgrade.logs().into_iter()
+        .filter_map(NepXChainEvent::from_log)
+        .any(|event| matches!(event, NepXChainEvent::ReceiptTokenUpgraded { ref receipt_token, .. } if receipt_token.as_str() == token_id.as_str()));
+    assert!(upgraded, "Expected receipt_token_upgraded event not found");
+    assert!(upgrade.receipt_failures().is_empty(), "{:?}", upgrade.receipt_failures());
+
+    // `migrate` kept the token's state.
+    let migrated: serde_json::Value = worker.view(&token_id, "xc_metadata").await?.json()?;
+    assert_eq!(migrated, xc_metadata);
+    let code_hash: String = bridge.view("get_receipt_token_code_hash").await?.json()?;
+    assert_eq!(worker.view_account(&token_id).await?.code_hash.to_string(), code_hash);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bridge_pause() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let owner = worker.root_account()?;
+    let bridge = deploy_bridge(&worker, &owner).await?;
 
     let is_paused: bool = bridge.view("is_paused")
         .await?
         .json()?;
     assert!(!is_paused, "Bridge should not be paused initially");
 
-    bridge.call("set_paused")
+    owner.call(bridge.id(), "set_paused")
         .args_json(json!({"paused": true}))
         .transact()
-        .await?;
+        .await?
+        .into_result()?;
 
     let is_paused: bool = bridge.view("is_paused")
         .await?
         .json()?;
     assert!(is_paused, "Bridge should be paused after set_paused(true)");
 
-    println!("Pause test passed!");
     Ok(())
 }
 
+async fn owner_call_events(
+    owner: &Account,
+    bridge: &Contract,
+    method: &str,
+    args: serde_json::Value,
+) -> anyhow::Result<Vec<NepXChainEvent>> {
+    let outcome = owner.call(bridge.id(), method)
+        .args_json(args)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(outcome.logs().into_iter().filter_map(NepXChainEvent::from_log).collect())
+}
+
 #[tokio::test]
-async fn test_messaging_contract() -> anyhow::Result<()> {
-    let worker = near_workspaces::sandbox().await?;
-    let owner = worker.root_account()?;
+async fn test_owner_actions_log_old_and_new_values() -> anyhow::Result<()> {
+    use sha2::{Digest, Sha256};
+    let env = setup_bridge().await?;
+    let BridgeTestEnv { owner, bridge, user, .. } = &env;
+    let account_id = |account: &Account| -> near_sdk::AccountId { account.id().as_str().parse().unwrap() };
 
-    let msg_wasm = near_workspaces::compile_project("./contracts/xchain-messaging").await?;
-    let messenger = worker.dev_deploy(&msg_wasm).await?;
-    
-    messenger.call("new")
+    let events = owner_call_events(owner, bridge, "set_paused", json!({"paused": true})).await?;
+    assert_eq!(events, vec![NepXChainEvent::Paused {
+        caller: account_id(owner),
+        old_paused: false,
+        new_paused: true,
+    }]);
+
+    let events = owner_call_events(owner, bridge, "set_treasury", json!({"treasury": user.id()})).await?;
+    assert_eq!(events, vec![NepXChainEvent::TreasuryChanged {
+        caller: account_id(owner),
+        old_treasury: account_id(owner),
+        new_treasury: account_id(user),
+    }]);
+
+    let old_code_hash: Option<Base58CryptoHash> = bridge.view("get_receipt_token_code_hash").await?.json()?;
+    assert!(old_code_hash.is_some());
+    let code = b"receipt token code".to_vec();
+    let events = owner_call_events(owner, bridge, "set_receipt_token_code", json!({"code": code})).await?;
+    assert_eq!(events, vec![NepXChainEvent::ReceiptTokenCodeChanged {
+        caller: account_id(owner),
+        old_code_hash,
+        new_code_hash: Some(<[u8; 32]>::from(Sha256::digest(&code)).into()),
+    }]);
+
+    add_bonded_validator(&env, user, 9, NearToken::from_near(1)).await?;
+    let events = owner_call_events(owner, bridge, "set_quorum", json!({"quorum": 2})).await?;
+    assert!(events.contains(&NepXChainEvent::QuorumChanged {
+        caller: account_id(owner),
+        epoch: 1,
+        old_quorum: 1,
+        new_quorum: 2,
+    }), "{:?}", events);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_in_order_nonces_use_no_bitmap_words() -> anyhow::Result<()> {
+    let BridgeTestEnv { bridge, user, .. } = setup_bridge().await?;
+
+    for nonce in 1..=5u128 {
+        let payload = BridgePayload {
+            nonce: U128(nonce),
+            source_chain: "eip155:1".parse().unwrap(),
+            target_chain: "near:testnet".parse().unwrap(),
+            asset: CanonicalAssetId {
+                source_chain_id: "eip155:1".parse().unwrap(),
+                source_contract: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+                asset_standard: AssetStandard::ERC20,
+            },
+            amount: U128(1_000),
+            receiver: user.id().to_string().parse().unwrap(),
+            source_tx_hash: format!("0x{nonce:064x}"),
+        };
+        let proof_data = near_sdk::borsh::to_vec(&payload).expect("Failed to serialize payload");
+        user.call(bridge.id(), "bridge_in")
+            .args_json(json!({
+                "proof": {
+                    "source_tx_hash": payload.source_tx_hash,
+                    "proof_data": proof_data,
+                    "block_height": 18500000,
+                    "committee_proof": committee_proof(bridge.id(), 0, &proof_data)
+                },
+                "receiver_id": user.id()
+            }))
+            // Only the first transfer deploys the receipt token.
+            .deposit(if nonce == 1 { NearToken::from_near(5) } else { NearToken::from_yoctonear(0) })
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    let usage: serde_json::Value = bridge.view("get_replay_storage_usage").args_json(json!({})).await?.json()?;
+    assert_eq!(usage["chains"], 1);
+    assert_eq!(usage["bitmap_words"], 0);
+
+    let watermark: U128 = bridge.view("get_nonce_watermark")
+        .args_json(json!({"source_chain": "eip155:1"}))
+        .await?
+        .json()?;
+    assert_eq!(watermark.0, 6);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_committee_fails_closed() -> anyhow::Result<()> {
+    let BridgeTestEnv { owner, bridge, user, .. } = setup_bridge().await?;
+
+    let payload = BridgePayload {
+        nonce: U128(1),
+        source_chain: "eip155:1".parse().unwrap(),
+        target_chain: "near:testnet".parse().unwrap(),
+        asset: CanonicalAssetId {
+            source_chain_id: "eip155:1".parse().unwrap(),
+            source_contract: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            asset_standard: AssetStandard::ERC20,
+        },
+        amount: U128(1_000_000_000),
+        receiver: user.id().to_string().parse().unwrap(),
+        source_tx_hash: "0xabc123def456".to_string(),
+    };
+    let proof_data = near_sdk::borsh::to_vec(&payload).expect("Failed to serialize payload");
+
+    let unsigned = bridge.call("bridge_in")
         .args_json(json!({
-            "owner_id": owner.id(),
-            "initial_validators": []
+            "proof": {"source_tx_hash": "0xabc123def456", "proof_data": proof_data, "block_height": 18500000},
+            "receiver_id": user.id()
         }))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
         .transact()
         .await?;
+    let failure = format!("{:?}", unsigned.into_result().unwrap_err());
+    assert!(failure.contains("Missing committee proof"), "{}", failure);
 
-    let send_result = messenger.call("send_message")
+    // A signature bound to another contract does not verify here.
+    let foreign = bridge.call("bridge_in")
         .args_json(json!({
-            "destination_chain": "ethereum:1",
-            "destination_contract": "0x1234567890abcdef",
-            "payload": [1, 2, 3, 4, 5]
+            "proof": {
+                "source_tx_hash": "0xabc123def456",
+                "proof_data": proof_data,
+                "block_height": 18500000,
+                "committee_proof": committee_proof(user.id(), 0, &proof_data)
+            },
+            "receiver_id": user.id()
         }))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
         .transact()
         .await?;
-    
-    let has_send_event = send_result.logs().iter()
-        .any(|log| log.contains("nep_xchain_msg") && log.contains("send_message"));
-    assert!(has_send_event, "Expected send_message event not found");
+    assert!(foreign.is_failure(), "Proof signed for another contract was accepted");
+
+    let remove_last = owner.call(bridge.id(), "remove_validator")
+        .args_json(json!({"public_key": validator_public_key()}))
+        .transact()
+        .await?;
+    let failure = format!("{:?}", remove_last.into_result().unwrap_err());
+    assert!(failure.contains("Validator set cannot be empty"), "{}", failure);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_validator_bond_requires_key_signature() -> anyhow::Result<()> {
+    use sha2::{Digest, Sha256};
+    let BridgeTestEnv { owner, bridge, user, .. } = setup_bridge().await?;
+
+    let new_validator = SigningKey::from_bytes(&[8u8; 32]);
+    let public_key = format!("ed25519:{}", bs58::encode(new_validator.verifying_key().as_bytes()).into_string());
+    let bond_message = |signer: &SigningKey, owner_id: &near_workspaces::AccountId| {
+        let mut data = b"nep_xchain_bond".to_vec();
+        data.extend(near_sdk::borsh::to_vec(&(owner_id.to_string(), bridge.id().to_string())).unwrap());
+        signer.sign(&Sha256::digest(&data)).to_bytes().to_vec()
+    };
+
+    // Squatting on someone else's key: the signature must come from the key itself.
+    let squat = user.call(bridge.id(), "bond")
+        .args_json(json!({"public_key": public_key, "signature": bond_message(&validator_key(), user.id())}))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    let failure = format!("{:?}", squat.into_result().unwrap_err());
+    assert!(failure.contains("Invalid key signature"), "{}", failure);
+
+    let unsigned = user.call(bridge.id(), "bond")
+        .args_json(json!({"public_key": public_key}))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?;
+    let failure = format!("{:?}", unsigned.into_result().unwrap_err());
+    assert!(failure.contains("Missing key signature"), "{}", failure);
+
+    user.call(bridge.id(), "bond")
+        .args_json(json!({"public_key": public_key, "signature": bond_message(&new_validator, user.id())}))
+        .deposit(NearToken::from_yoctonear(1))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let underbonded = owner.call(bridge.id(), "add_validator")
+        .args_json(json!({"public_key": public_key}))
+        .transact()
+        .await?;
+    let failure = format!("{:?}", underbonded.into_result().unwrap_err());
+    assert!(failure.contains("Validator bond below minimum"), "{}", failure);
+
+    let zero_minimum = owner.call(bridge.id(), "set_min_validator_bond")
+        .args_json(json!({"min_validator_bond": "0"}))
+        .transact()
+        .await?;
+    assert!(zero_minimum.is_failure(), "A zero minimum bond was accepted");
+
+    // Top-ups by the bond owner need no signature.
+    user.call(bridge.id(), "bond")
+        .args_json(json!({"public_key": public_key}))
+        .deposit(NearToken::from_near(1))
+        .transact()
+        .await?
+        .into_result()?;
+
+    owner.call(bridge.id(), "set_max_validators")
+        .args_json(json!({"max_validators": 1}))
+        .transact()
+        .await?
+        .into_result()?;
+    let over_limit = owner.call(bridge.id(), "add_validator")
+        .args_json(json!({"public_key": public_key}))
+        .transact()
+        .await?;
+    let failure = format!("{:?}", over_limit.into_result().unwrap_err());
+    assert!(failure.contains("Too many validators"), "{}", failure);
+
+    owner.call(bridge.id(), "set_max_validators")
+        .args_json(json!({"max_validators": 2}))
+        .transact()
+        .await?
+        .into_result()?;
+    owner.call(bridge.id(), "add_validator")
+        .args_json(json!({"public_key": public_key}))
+        .transact()
+        .await?
+        .into_result()?;
+    let epoch: serde_json::Value = bridge.view("get_validator_epoch").args_json(json!({"epoch": null})).await?.json()?;
+    assert_eq!(epoch["validators"].as_array().map(Vec::len), Some(2));
+    // The initial quorum of one weight is kept when the set grows.
+    assert_eq!(epoch["quorum"], 1);
+
+    Ok(())
+}
+
+/// A plain USDC transfer to `receiver`, as the committee would sign it.
+fn usdc_payload(receiver: &near_workspaces::AccountId, nonce: u128, amount: u128, source_tx_hash: &str) -> BridgePayload {
+    BridgePayload {
+        nonce: U128(nonce),
+        source_chain: "eip155:1".parse().unwrap(),
+        target_chain: "near:testnet".parse().unwrap(),
+        asset: CanonicalAssetId {
+            source_chain_id: "eip155:1".parse().unwrap(),
+            source_contract: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+            asset_standard: AssetStandard::ERC20,
+        },
+        amount: U128(amount),
+        receiver: receiver.to_string().parse().unwrap(),
+        source_tx_hash: source_tx_hash.to_string(),
+    }
+}
+
+/// Submits `payload` to `bridge_in` with a committee proof.
+async fn bridge_in_payload(
+    bridge: &Contract,
+    payload: &BridgePayload,
+) -> anyhow::Result<near_workspaces::result::ExecutionFinalResult> {
+    let proof_data = near_sdk::borsh::to_vec(payload)?;
+    Ok(bridge.call("bridge_in")
+        .args_json(json!({
+            "proof": {
+                "source_tx_hash": payload.source_tx_hash,
+                "proof_data": proof_data,
+                "block_height": 18500000,
+                "committee_proof": committee_proof(bridge.id(), 0, &proof_data)
+            },
+            "receiver_id": payload.receiver
+        }))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?)
+}
+
+/// Bonds a fresh validator key owned by `operator` with `stake` and adds it
+/// to the committee in epoch 1.
+async fn add_bonded_validator(
+    env: &BridgeTestEnv,
+    operator: &Account,
+    seed: u8,
+    stake: NearToken,
+) -> anyhow::Result<(SigningKey, String)> {
+    use sha2::{Digest, Sha256};
+    let signer = SigningKey::from_bytes(&[seed; 32]);
+    let public_key = format!("ed25519:{}", bs58::encode(signer.verifying_key().as_bytes()).into_string());
+    let mut data = b"nep_xchain_bond".to_vec();
+    data.extend(near_sdk::borsh::to_vec(&(operator.id().to_string(), env.bridge.id().to_string())).unwrap());
+    operator.call(env.bridge.id(), "bond")
+        .args_json(json!({"public_key": public_key, "signature": signer.sign(&Sha256::digest(&data)).to_bytes().to_vec()}))
+        .deposit(stake)
+        .transact()
+        .await?
+        .into_result()?;
+    env.owner.call(env.bridge.id(), "add_validator")
+        .args_json(json!({"public_key": public_key}))
+        .transact()
+        .await?
+        .into_result()?;
+    Ok((signer, public_key))
+}
+
+#[tokio::test]
+async fn test_equivocation_slashes_bond() -> anyhow::Result<()> {
+    let env = setup_bridge().await?;
+    let BridgeTestEnv { owner, bridge, user, .. } = &env;
+    let treasury = owner.create_subaccount("treasury").transact().await?.into_result()?;
+    owner.call(bridge.id(), "set_treasury")
+        .args_json(json!({"treasury": treasury.id()}))
+        .transact()
+        .await?
+        .into_result()?;
+    let (signer, public_key) = add_bonded_validator(&env, user, 8, NearToken::from_near(2)).await?;
+
+    // The same transfer signed twice with different amounts.
+    let first = near_sdk::borsh::to_vec(&usdc_payload(user.id(), 1, 1_000_000, "0xequivocation")).unwrap();
+    let second = near_sdk::borsh::to_vec(&usdc_payload(user.id(), 1, 9_000_000, "0xequivocation")).unwrap();
+    let signed = |proof_data: &Vec<u8>| json!({
+        "epoch": 1,
+        "proof_data": proof_data,
+        "signature": validator_signature(&signer, bridge.id(), 1, proof_data)
+    });
+
+    let unrelated = near_sdk::borsh::to_vec(&usdc_payload(user.id(), 2, 9_000_000, "0xother")).unwrap();
+    let different_transfers = user.call(bridge.id(), "report_equivocation")
+        .args_json(json!({"public_key": public_key, "first": signed(&first), "second": signed(&unrelated)}))
+        .transact()
+        .await?;
+    let failure = format!("{:?}", different_transfers.into_result().unwrap_err());
+    assert!(failure.contains("Payloads are for different transfers"), "{}", failure);
+
+    let treasury_before = treasury.view_account().await?.balance;
+    let report = user.call(bridge.id(), "report_equivocation")
+        .args_json(json!({"public_key": public_key, "first": signed(&first), "second": signed(&second)}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let slashed = report.logs().into_iter()
+        .filter_map(NepXChainEvent::from_log)
+        .find_map(|event| match event {
+            NepXChainEvent::ValidatorSlashed { amount, source_tx_hash, nonce, .. } => Some((amount, source_tx_hash, nonce)),
+            _ => None,
+        });
+    assert_eq!(
+        slashed,
+        Some((U128(NearToken::from_near(2).as_yoctonear()), "0xequivocation".to_string(), U128(1)))
+    );
+
+    let bond: Option<serde_json::Value> = bridge.view("get_bond").args_json(json!({"public_key": public_key})).await?.json()?;
+    assert!(bond.is_none(), "Slashed bond was kept");
+    let epoch: serde_json::Value = bridge.view("get_validator_epoch").args_json(json!({"epoch": null})).await?.json()?;
+    assert_eq!(epoch["epoch"], 2);
+    assert_eq!(epoch["validators"].as_array().map(Vec::len), Some(1));
+    let treasury_after = treasury.view_account().await?.balance;
+    assert_eq!(treasury_after.as_yoctonear() - treasury_before.as_yoctonear(), NearToken::from_near(2).as_yoctonear());
+
+    // The same evidence cannot be reported twice.
+    let double_report = user.call(bridge.id(), "report_equivocation")
+        .args_json(json!({"public_key": public_key, "first": signed(&first), "second": signed(&second)}))
+        .max_gas()
+        .transact()
+        .await?;
+    let failure = format!("{:?}", double_report.into_result().unwrap_err());
+    assert!(failure.contains("Validator already slashed"), "{}", failure);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unbonded_stake_is_locked_until_unbonding_period() -> anyhow::Result<()> {
+    let env = setup_bridge().await?;
+    let BridgeTestEnv { worker, owner, bridge, user } = &env;
+    owner.call(bridge.id(), "set_unbonding_period")
+        .args_json(json!({"unbonding_period": 60_000_000_000u64}))
+        .transact()
+        .await?
+        .into_result()?;
+    let (_, public_key) = add_bonded_validator(&env, user, 8, NearToken::from_near(3)).await?;
+    let unbond = |amount: NearToken| user.call(bridge.id(), "unbond")
+        .args_json(json!({"public_key": public_key, "amount": U128(amount.as_yoctonear())}))
+        .transact();
+    let withdraw = || user.call(bridge.id(), "withdraw_stake")
+        .args_json(json!({"public_key": public_key}))
+        .transact();
+
+    let failure = format!("{:?}", unbond(NearToken::from_millinear(2500)).await?.into_result().unwrap_err());
+    assert!(failure.contains("Active validators must keep the minimum bond"), "{}", failure);
+    unbond(NearToken::from_near(1)).await?.into_result()?;
+    let failure = format!("{:?}", withdraw().await?.into_result().unwrap_err());
+    assert!(failure.contains("Stake is still unbonding"), "{}", failure);
+
+    // Shortening the period does not release stake that is already unbonding.
+    let bond: serde_json::Value = bridge.view("get_bond").args_json(json!({"public_key": public_key})).await?.json()?;
+    let unlock_at = bond["unlock_at"].as_u64().expect("unlock_at not set");
+    owner.call(bridge.id(), "set_unbonding_period")
+        .args_json(json!({"unbonding_period": 0}))
+        .transact()
+        .await?
+        .into_result()?;
+    unbond(NearToken::from_millinear(500)).await?.into_result()?;
+    let bond: serde_json::Value = bridge.view("get_bond").args_json(json!({"public_key": public_key})).await?.json()?;
+    assert_eq!(bond["unlock_at"].as_u64(), Some(unlock_at));
+    let failure = format!("{:?}", withdraw().await?.into_result().unwrap_err());
+    assert!(failure.contains("Stake is still unbonding"), "{}", failure);
+
+    while worker.view_block().await?.timestamp() < unlock_at {
+        worker.fast_forward(100).await?;
+    }
+    let balance_before = user.view_account().await?.balance;
+    withdraw().await?.into_result()?;
+    let balance_after = user.view_account().await?.balance;
+    assert!(
+        balance_after.as_yoctonear() > balance_before.as_yoctonear() + NearToken::from_millinear(1490).as_yoctonear(),
+        "Unbonded stake was not paid out"
+    );
+    let bond: serde_json::Value = bridge.view("get_bond").args_json(json!({"public_key": public_key})).await?.json()?;
+    assert_eq!(bond["staked"], NearToken::from_millinear(1500).as_yoctonear().to_string());
+    assert_eq!(bond["unbonding"], "0");
+    let failure = format!("{:?}", withdraw().await?.into_result().unwrap_err());
+    assert!(failure.contains("Nothing to withdraw"), "{}", failure);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_messaging_contract() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let owner = worker.root_account()?;
+
+    let msg_wasm = near_workspaces::compile_project("./contracts/xchain-messaging").await?;
+    let messenger = worker.dev_deploy(&msg_wasm).await?;
+    
+    messenger.call("new")
+        .args_json(json!({
+            "owner_id": owner.id(),
+            "initial_validators": [],
+            "chain_id": "near:testnet"
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    let sender = owner.create_subaccount("alice")
+        .initial_balance(NearToken::from_near(10))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let send_result = sender.call(messenger.id(), "send_message")
+        .args_json(json!({
+            "destination_chain": "eip155:1",
+            "destination_contract": "0x1234567890abcdef",
+            "payload": [1, 2, 3, 4, 5]
+        }))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?;
+    
+    let has_send_event = send_result.logs().iter()
+        .filter_map(|log| NepXChainEvent::from_log(log))
+        .any(|event| matches!(event, NepXChainEvent::MessageSent { nonce, .. } if nonce.0 == 1));
+    assert!(has_send_event, "Expected message_sent event not found");
+
+    let receipt: MessageReceipt = send_result.json()?;
+    assert_eq!(receipt.nonce.0, 1);
+    let outbound: serde_json::Value = messenger.view("get_outbound_message")
+        .args_json(json!({"destination_chain": "eip155:1", "nonce": "1"}))
+        .await?
+        .json()?;
+    let message: XChainMessage = serde_json::from_value(outbound["message"].clone())?;
+    assert_eq!(message.payload, vec![1, 2, 3, 4, 5]);
+    assert_eq!(message.source_chain.as_str(), "near:testnet");
+    assert_eq!(message.source_contract, sender.id().to_string());
+    assert_eq!(message.ordering, MessageOrdering::Unordered);
+    // With no fee set the deposit only has to cover the stored message and,
+    // as the first message to the chain, the chain's nonce record.
+    let storage_cost: u128 = outbound["storage_cost"].as_str().unwrap().parse()?;
+    let nonce_record_cost = record_storage_cost(b"d", &message.destination_chain, 16);
+    assert!(storage_cost > nonce_record_cost, "The stored message should be charged");
+    let message_storage_cost = storage_cost - nonce_record_cost;
+    assert_eq!(outbound["fee"], "0");
+    assert_eq!(outbound["prepaid_gas"], (NearToken::from_millinear(10).as_yoctonear() - storage_cost).to_string());
+
+    owner.call(messenger.id(), "set_message_fee")
+        .args_json(json!({
+            "destination_chain": "eip155:1",
+            "fee": {"base_fee": "1000", "per_byte_fee": "10"}
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    let fee: U128 = messenger.view("estimate_message_fee")
+        .args_json(json!({"destination_chain": "eip155:1", "payload_size": 5}))
+        .await?
+        .json()?;
+    assert_eq!(fee.0, 1050);
+
+    let unpaid = sender.call(messenger.id(), "send_message")
+        .args_json(json!({"destination_chain": "eip155:1", "destination_contract": "0x1234567890abcdef", "payload": [1, 2, 3, 4, 5]}))
+        .transact()
+        .await?;
+    assert!(unpaid.is_failure(), "Sending without the fee should fail");
+    let short = sender.call(messenger.id(), "send_message")
+        .args_json(json!({"destination_chain": "eip155:1", "destination_contract": "0x1234567890abcdef", "payload": [1, 2, 3, 4, 5]}))
+        .deposit(NearToken::from_yoctonear(message_storage_cost + 1049))
+        .transact()
+        .await?;
+    let failure = format!("{:?}", short.into_result().unwrap_err());
+    assert!(failure.contains("Insufficient message fee"), "{}", failure);
+
+    // Later messages of the same shape only pay for the stored message.
+    sender.call(messenger.id(), "send_message")
+        .args_json(json!({"destination_chain": "eip155:1", "destination_contract": "0x1234567890abcdef", "payload": [1, 2, 3, 4, 5]}))
+        .deposit(NearToken::from_yoctonear(message_storage_cost + 1550))
+        .transact()
+        .await?
+        .into_result()?;
+    let outbound: serde_json::Value = messenger.view("get_outbound_message")
+        .args_json(json!({"destination_chain": "eip155:1", "nonce": "2"}))
+        .await?
+        .json()?;
+    assert_eq!(outbound["fee"], "1050");
+    assert_eq!(outbound["storage_cost"], message_storage_cost.to_string());
+    assert_eq!(outbound["prepaid_gas"], "500");
+
+    // Once the destination has an allowlist only listed senders get through;
+    // the contract address matches regardless of hex case.
+    let outsider = owner.create_subaccount("bob")
+        .initial_balance(NearToken::from_near(10))
+        .transact()
+        .await?
+        .into_result()?;
+    owner.call(messenger.id(), "set_sender_allowlist")
+        .args_json(json!({
+            "destination_chain": "eip155:1",
+            "destination_contract": "0x1234567890ABCDEF",
+            "senders": [sender.id()]
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    for (account, allowed) in [(&sender, true), (&outsider, false)] {
+        let result = account.call(messenger.id(), "send_message")
+            .args_json(json!({"destination_chain": "eip155:1", "destination_contract": "0x1234567890abcdef", "payload": [1, 2, 3, 4, 5]}))
+            .deposit(NearToken::from_yoctonear(message_storage_cost + 1050))
+            .transact()
+            .await?;
+        if allowed {
+            let receipt: MessageReceipt = result.into_result()?.json()?;
+            assert_eq!(receipt.nonce.0, 3);
+        } else {
+            let failure = format!("{:?}", result.into_result().unwrap_err());
+            assert!(failure.contains("Sender not allowed for destination"), "{}", failure);
+        }
+    }
+
+    // Fees and prepaid gas are tracked apart; the storage costs stay behind.
+    let collected: U128 = messenger.view("get_collected_fees").await?.json()?;
+    assert_eq!(collected.0, 2100);
+    let prepaid: U128 = messenger.view("get_prepaid_gas").await?.json()?;
+    assert_eq!(prepaid.0, NearToken::from_millinear(10).as_yoctonear() - storage_cost + 500);
+    for method in ["withdraw_fees", "withdraw_prepaid_gas"] {
+        owner.call(messenger.id(), method)
+            .args_json(json!({"amount": null}))
+            .transact()
+            .await?
+            .into_result()?;
+    }
+    let collected: U128 = messenger.view("get_collected_fees").await?.json()?;
+    assert_eq!(collected.0, 0);
+    let prepaid: U128 = messenger.view("get_prepaid_gas").await?.json()?;
+    assert_eq!(prepaid.0, 0);
+
+    // The first ordered message on a channel also pays for the channel's
+    // sequence record; its `Ordered` ordering is 16 bytes longer.
+    let receipt: MessageReceipt = sender.call(messenger.id(), "send_message")
+        .args_json(json!({"destination_chain": "eip155:1", "destination_contract": "0x1234567890abcdef", "payload": [1, 2, 3, 4, 5], "ordered": true}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?
+        .into_result()?
+        .json()?;
+    let outbound: serde_json::Value = messenger.view("get_outbound_message")
+        .args_json(json!({"destination_chain": "eip155:1", "nonce": receipt.nonce}))
+        .await?
+        .json()?;
+    let channel = (message.destination_chain.clone(), sender.id().to_string(), "0x1234567890abcdef".to_string());
+    let sequence_record_cost = record_storage_cost(b"o", &channel, 16);
+    assert_eq!(
+        outbound["storage_cost"],
+        (message_storage_cost + 16 * STORAGE_BYTE_COST + sequence_record_cost).to_string()
+    );
+
+    Ok(())
+}
+
+const STORAGE_BYTE_COST: u128 = 10_000_000_000_000_000_000;
+
+/// What a `LookupMap` record under `prefix` costs: its borsh key and
+/// `value_len`-byte value plus the 40 bytes the runtime adds per record.
+fn record_storage_cost(prefix: &[u8], key: &impl near_sdk::borsh::BorshSerialize, value_len: usize) -> u128 {
+    let key_len = prefix.len() + near_sdk::borsh::to_vec(key).unwrap().len();
+    (key_len + value_len + 40) as u128 * STORAGE_BYTE_COST
+}
+
+/// A committee-signed inbound message for the messenger on `near:testnet`.
+fn inbound_message(nonce: u128, destination_chain: &str, ordering: MessageOrdering) -> XChainMessage {
+    XChainMessage {
+        nonce: U128(nonce),
+        source_chain: "eip155:1".parse().unwrap(),
+        destination_chain: destination_chain.parse().unwrap(),
+        source_contract: "0x1234567890abcdef".to_string(),
+        destination_contract: "receiver.testnet".to_string(),
+        payload: vec![1, 2, 3],
+        ordering,
+    }
+}
+
+async fn receive_message(
+    messenger: &Contract,
+    message: &XChainMessage,
+) -> anyhow::Result<near_workspaces::result::ExecutionFinalResult> {
+    let proof_data = near_sdk::borsh::to_vec(message).expect("Failed to serialize message");
+    Ok(messenger.call("receive_message")
+        .args_json(json!({
+            "proof": {
+                "source_tx_hash": format!("0xmsg{}", message.nonce.0),
+                "proof_data": proof_data,
+                "block_height": 18500000,
+                "committee_proof": committee_proof(messenger.id(), 0, &proof_data)
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?)
+}
+
+#[tokio::test]
+async fn test_receive_message() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let owner = worker.root_account()?;
+
+    let msg_wasm = near_workspaces::compile_project("./contracts/xchain-messaging").await?;
+    let messenger = worker.dev_deploy(&msg_wasm).await?;
+    messenger.call("new")
+        .args_json(json!({
+            "owner_id": owner.id(),
+            "initial_validators": [validator_public_key()],
+            "chain_id": "near:testnet"
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // A message signed for another chain's messenger is rejected.
+    let result = receive_message(&messenger, &inbound_message(1, "near:mainnet", MessageOrdering::Unordered)).await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("Wrong destination chain"), "{}", failure);
+
+    let result = receive_message(&messenger, &inbound_message(1, "near:testnet", MessageOrdering::Unordered)).await?;
+    let has_receive_event = result.logs().iter()
+        .filter_map(|log| NepXChainEvent::from_log(log))
+        .any(|event| matches!(event, NepXChainEvent::MessageReceived { nonce, .. } if nonce.0 == 1));
+    assert!(has_receive_event, "Expected message_received event not found");
+
+    // Unordered messages may arrive out of order, but each nonce only once.
+    for nonce in [3, 2] {
+        receive_message(&messenger, &inbound_message(nonce, "near:testnet", MessageOrdering::Unordered))
+            .await?
+            .into_result()?;
+    }
+    for (nonce, processed) in [("1", true), ("2", true), ("3", true), ("4", false)] {
+        let is_processed: bool = messenger.view("is_message_processed")
+            .args_json(json!({"source_chain": "eip155:1", "nonce": nonce}))
+            .await?
+            .json()?;
+        assert_eq!(is_processed, processed, "nonce {}", nonce);
+    }
+    let result = receive_message(&messenger, &inbound_message(2, "near:testnet", MessageOrdering::Unordered)).await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("Message already processed"), "{}", failure);
+
+    // An ordered channel only accepts the next sequence.
+    let ordered = |nonce: u128, sequence: u128| {
+        inbound_message(nonce, "near:testnet", MessageOrdering::Ordered { sequence: U128(sequence) })
+    };
+    receive_message(&messenger, &ordered(4, 1)).await?.into_result()?;
+    let result = receive_message(&messenger, &ordered(5, 3)).await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("Unexpected channel sequence"), "{}", failure);
+    let is_processed: bool = messenger.view("is_message_processed")
+        .args_json(json!({"source_chain": "eip155:1", "nonce": "5"}))
+        .await?
+        .json()?;
+    assert!(!is_processed, "A rejected message must not use up its nonce");
+    receive_message(&messenger, &ordered(5, 2)).await?.into_result()?;
+    let sequence: U128 = messenger.view("get_inbound_sequence")
+        .args_json(json!({
+            "source_chain": "eip155:1",
+            "source_contract": "0x1234567890ABCDEF",
+            "destination_contract": "receiver.testnet"
+        }))
+        .await?
+        .json()?;
+    assert_eq!(sequence.0, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pending_claim_after_failed_mint() -> anyhow::Result<()> {
+    let BridgeTestEnv { worker, bridge, user, .. } = setup_bridge().await?;
+
+    let canonical_asset = CanonicalAssetId {
+        source_chain_id: "eip155:1".parse().unwrap(),
+        source_contract: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+        asset_standard: AssetStandard::ERC20,
+    };
+
+    let payload = BridgePayload {
+        nonce: U128(1),
+        source_chain: "eip155:1".parse().unwrap(),
+        target_chain: "near:testnet".parse().unwrap(),
+        asset: canonical_asset.clone(),
+        amount: U128(1_000_000_000),
+        receiver: user.id().to_string().parse().unwrap(),
+        source_tx_hash: "0xabc123def456".to_string(),
+    };
+
+    let proof_data = near_sdk::borsh::to_vec(&payload).expect("Failed to serialize payload");
+
+    bridge.call("bridge_in")
+        .args_json(json!({
+            "proof": {
+                "source_tx_hash": "0xabc123def456",
+                "proof_data": proof_data,
+                "block_height": 18500000,
+                "committee_proof": committee_proof(bridge.id(), 0, &proof_data)
+            },
+            "receiver_id": user.id()
+        }))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // alice never registered storage on the receipt token, so the mint fails and is escrowed.
+    let claims: serde_json::Value = bridge.view("get_pending_claims")
+        .args_json(json!({"account_id": user.id()}))
+        .await?
+        .json()?;
+    assert_eq!(claims.as_array().map(|c| c.len()), Some(1), "Expected one pending claim");
+    assert_eq!(claims[0]["amount"], "1000000000");
+    let receipt_token = claims[0]["receipt_token"].as_str().unwrap().to_string();
+
+    user.call(&receipt_token.parse()?, "storage_deposit")
+        .args_json(json!({"account_id": user.id()}))
+        .deposit(NearToken::from_millinear(10))
+        .transact()
+        .await?
+        .into_result()?;
+
+    user.call(bridge.id(), "claim")
+        .args_json(json!({"canonical_asset": canonical_asset}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance: U128 = worker.view(&receipt_token.parse()?, "ft_balance_of")
+        .args_json(json!({"account_id": user.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance.0, 1_000_000_000);
+
+    let claims: serde_json::Value = bridge.view("get_pending_claims")
+        .args_json(json!({"account_id": user.id()}))
+        .await?
+        .json()?;
+    assert_eq!(claims.as_array().map(|c| c.len()), Some(0), "Claim should be settled");
+
+    Ok(())
+}
+
+/// Bridges `amount` of USDC in to `receiver` with `ft_transfer_call(msg)`.
+async fn bridge_in_with_call(
+    bridge: &Contract,
+    receiver: &near_workspaces::AccountId,
+    nonce: u128,
+    amount: u128,
+    msg: &str,
+) -> anyhow::Result<near_workspaces::result::ExecutionFinalResult> {
+    let source_tx_hash = format!("0xcall{}", nonce);
+    let payload = TokenCallPayload {
+        transfer: BridgePayload {
+            nonce: U128(nonce),
+            source_chain: "eip155:1".parse().unwrap(),
+            target_chain: "near:testnet".parse().unwrap(),
+            asset: CanonicalAssetId {
+                source_chain_id: "eip155:1".parse().unwrap(),
+                source_contract: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+                asset_standard: AssetStandard::ERC20,
+            },
+            amount: U128(amount),
+            receiver: receiver.to_string().parse().unwrap(),
+            source_tx_hash: source_tx_hash.clone(),
+        },
+        msg: Some(msg.to_string()),
+    };
+    let proof_data = near_sdk::borsh::to_vec(&payload).expect("Failed to serialize payload");
+    Ok(bridge.call("bridge_in")
+        .args_json(json!({
+            "proof": {
+                "source_tx_hash": source_tx_hash,
+                "proof_data": proof_data,
+                "block_height": 18500000,
+                "committee_proof": committee_proof(bridge.id(), 0, &proof_data)
+            },
+            "receiver_id": receiver
+        }))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?)
+}
+
+#[tokio::test]
+async fn test_bridge_in_with_token_call() -> anyhow::Result<()> {
+    let BridgeTestEnv { worker, owner, bridge, user } = setup_bridge().await?;
+
+    // The registry stands in for a lending pool: it accepts `"AddLiquidity"`
+    // once the receipt token is verified and rejects anything else.
+    let registry_wasm = near_workspaces::compile_project("./contracts/xchain-registry").await?;
+    let registry = worker.dev_deploy(&registry_wasm).await?;
+    registry.call("new")
+        .args_json(json!({"owner_id": owner.id()}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Unverified token and a receiver without storage on it: the call fails,
+    // the refund cannot be delivered, so the bridge burns and escrows it.
+    bridge_in_with_call(&bridge, registry.id(), 1, 1000, "\"AddLiquidity\"").await?.into_result()?;
+    let claims: serde_json::Value = bridge.view("get_pending_claims")
+        .args_json(json!({"account_id": registry.id()}))
+        .await?
+        .json()?;
+    assert_eq!(claims.as_array().map(|c| c.len()), Some(1), "Expected one pending claim");
+    assert_eq!(claims[0]["amount"], "1000");
+    let token: near_workspaces::AccountId = claims[0]["receipt_token"].as_str().unwrap().parse()?;
+    let supply: U128 = worker.view(&token, "ft_total_supply").await?.json()?;
+    assert_eq!(supply.0, 0, "The undeliverable refund should be burned");
+
+    // Only authorized routes can mint.
+    let result = user.call(&token, "mint")
+        .args_json(json!({"account_id": user.id(), "amount": "1"}))
+        .transact()
+        .await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("Only bridge can mint"), "{}", failure);
+
+    owner.call(registry.id(), "register_token")
+        .args_json(json!({"token_id": token}))
+        .deposit(NearToken::from_millinear(125))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    owner.call(registry.id(), "register_bridge")
+        .args_json(json!({"bridge_contract": bridge.id()}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    owner.call(bridge.id(), "register_receipt_with_registry")
+        .args_json(json!({"registry_id": registry.id(), "token_id": token}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Delivered with ft_transfer_call: the receiver uses all of it.
+    bridge_in_with_call(&bridge, registry.id(), 2, 500, "\"AddLiquidity\"").await?.into_result()?;
+    let pool: U128 = registry.view("get_pool")
+        .args_json(json!({"token_id": token}))
+        .await?
+        .json()?;
+    assert_eq!(pool.0, 500);
+
+    // A failed call is refunded to the receiver, not kept by the bridge.
+    bridge_in_with_call(&bridge, registry.id(), 3, 300, "\"Withdraw\"").await?.into_result()?;
+    let balance: U128 = worker.view(&token, "ft_balance_of")
+        .args_json(json!({"account_id": registry.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance.0, 800);
+    let pool: U128 = registry.view("get_pool")
+        .args_json(json!({"token_id": token}))
+        .await?
+        .json()?;
+    assert_eq!(pool.0, 500);
+    let balance: U128 = worker.view(&token, "ft_balance_of")
+        .args_json(json!({"account_id": bridge.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance.0, 0, "The bridge should not keep tokens in transit");
+
+    Ok(())
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut out = rlp_length_prefix(0x80, bytes.len());
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload: Vec<u8> = items.concat();
+    let mut out = rlp_length_prefix(0xc0, payload.len());
+    out.extend(payload);
+    out
+}
+
+fn rlp_length_prefix(offset: u8, len: usize) -> Vec<u8> {
+    if len < 56 {
+        return vec![offset + len as u8];
+    }
+    let len_bytes: Vec<u8> = len.to_be_bytes().iter().copied().skip_while(|b| *b == 0).collect();
+    let mut out = vec![offset + 55 + len_bytes.len() as u8];
+    out.extend(len_bytes);
+    out
+}
+
+fn rlp_u64(value: u64) -> Vec<u8> {
+    let bytes: Vec<u8> = value.to_be_bytes().iter().copied().skip_while(|b| *b == 0).collect();
+    rlp_bytes(&bytes)
+}
+
+fn keccak(data: &[u8]) -> [u8; 32] {
+    use sha3::{Digest, Keccak256};
+    Keccak256::digest(data).into()
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    format!("0x{}", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+fn abi_word(value: u128) -> Vec<u8> {
+    let mut word = vec![0u8; 16];
+    word.extend_from_slice(&value.to_be_bytes());
+    word
+}
+
+#[tokio::test]
+async fn test_bridge_in_with_eth_receipt_proof() -> anyhow::Result<()> {
+    let BridgeTestEnv { worker, owner, bridge, user } = setup_bridge().await?;
+
+    let light_client_wasm = near_workspaces::compile_project("./contracts/xchain-mock-light-client").await?;
+    let light_client = worker.dev_deploy(&light_client_wasm).await?;
+    light_client.call("new")
+        .args_json(json!({"owner_id": light_client.id()}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let locker = [0x11u8; 20];
+    let token = [0xa0u8; 20];
+    let block_number = 18_500_000u64;
+
+    let recipient = user.id().as_str().as_bytes().to_vec();
+    let mut data = abi_word(1_000_000_000);
+    data.extend(abi_word(96));
+    data.extend(abi_word(0));
+    data.extend(abi_word(recipient.len() as u128));
+    let mut padded = recipient.clone();
+    padded.resize(recipient.len().div_ceil(32) * 32, 0);
+    data.extend(padded);
+
+    let mut token_topic = [0u8; 32];
+    token_topic[12..].copy_from_slice(&token);
+    let log = rlp_list(&[
+        rlp_bytes(&locker),
+        rlp_list(&[
+            rlp_bytes(&keccak(b"Locked(address,address,uint256,string,uint256)")),
+            rlp_bytes(&token_topic),
+            rlp_bytes(&[0u8; 32]),
+        ]),
+        rlp_bytes(&data),
+    ]);
+    let receipt = rlp_list(&[rlp_u64(1), rlp_u64(21_000), rlp_bytes(&[0u8; 256]), rlp_list(&[log])]);
+
+    // Single-receipt trie: one leaf holding the whole key rlp(0) = 0x80.
+    let leaf = rlp_list(&[rlp_bytes(&[0x20, 0x80]), rlp_bytes(&receipt)]);
+    let receipts_root = keccak(&leaf);
+
+    // The matching transactions trie, holding a legacy transaction to the locker.
+    let transaction = rlp_list(&[
+        rlp_u64(0),
+        rlp_u64(1_000_000_000),
+        rlp_u64(100_000),
+        rlp_bytes(&locker),
+        rlp_u64(0),
+        rlp_bytes(&[]),
+        rlp_u64(27),
+        rlp_bytes(&[1u8; 32]),
+        rlp_bytes(&[2u8; 32]),
+    ]);
+    let transaction_leaf = rlp_list(&[rlp_bytes(&[0x20, 0x80]), rlp_bytes(&transaction)]);
+    let transactions_root = keccak(&transaction_leaf);
+    let tx_hash = hex_string(&keccak(&transaction));
+
+    let header = rlp_list(&[
+        rlp_bytes(&[0u8; 32]),
+        rlp_bytes(&[0u8; 32]),
+        rlp_bytes(&[0u8; 20]),
+        rlp_bytes(&[0u8; 32]),
+        rlp_bytes(&transactions_root),
+        rlp_bytes(&receipts_root),
+        rlp_bytes(&[0u8; 256]),
+        rlp_u64(0),
+        rlp_u64(block_number),
+        rlp_u64(30_000_000),
+        rlp_u64(21_000),
+        rlp_u64(1_700_000_000),
+        rlp_bytes(&[]),
+        rlp_bytes(&[0u8; 32]),
+        rlp_bytes(&[0u8; 8]),
+    ]);
+    let block_hash = keccak(&header);
+
+    light_client.call("set_block_hash")
+        .args_json(json!({
+            "block_number": block_number,
+            "block_hash": hex_string(&block_hash)
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    owner.call(bridge.id(), "set_eth_prover")
+        .args_json(json!({
+            "config": {
+                "light_client": light_client.id(),
+                "locker_address": format!("0x{}", "11".repeat(20)),
+                "source_chain": "eip155:1"
+            }
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let eth_bridge_in = |source_tx_hash: &str| json!({
+        "proof": {
+            "source_tx_hash": source_tx_hash,
+            "proof_data": [],
+            "block_height": block_number,
+            "eth_proof": {
+                "header_rlp": header,
+                "receipt_index": 0,
+                "receipt_proof": [leaf],
+                "log_index": 0,
+                "transaction_proof": [transaction_leaf]
+            }
+        },
+        "receiver_id": user.id()
+    });
+
+    // The transaction hash comes from the proof; a different one is rejected.
+    let result = bridge.call("bridge_in")
+        .args_json(eth_bridge_in("0xabc123def456"))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("Source tx hash mismatch"), "{}", failure);
+
+    // The light client's head is still at the block: not final for a chain
+    // configured with 64 finality blocks, so nothing is minted.
+    let early_result = bridge.call("bridge_in")
+        .args_json(eth_bridge_in(&tx_hash))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?;
+    let minted_early = early_result.logs().iter()
+        .filter_map(|log| NepXChainEvent::from_log(log))
+        .any(|event| matches!(event, NepXChainEvent::BridgeIn { .. }));
+    assert!(!minted_early, "Transfer from a non-final block was minted");
+
+    light_client.call("set_last_block_number")
+        .args_json(json!({"block_number": block_number + 64}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let bridge_in_result = bridge.call("bridge_in")
+        .args_json(eth_bridge_in(""))
+        .deposit(NearToken::from_near(5))
+        .max_gas()
+        .transact()
+        .await?;
+
+    let bridged = bridge_in_result.logs().iter()
+        .filter_map(|log| NepXChainEvent::from_log(log))
+        .find_map(|event| match event {
+            NepXChainEvent::BridgeIn { canonical_asset, amount, source_tx_hash, .. } => {
+                Some((canonical_asset, amount, source_tx_hash))
+            }
+            _ => None,
+        });
+    let (canonical_asset, amount, source_tx_hash) = bridged.expect("Expected bridge_in event not found");
+    assert_eq!(amount.0, 1_000_000_000);
+    assert_eq!(source_tx_hash, tx_hash);
+    assert_eq!(canonical_asset.source_contract, format!("0x{}", "a0".repeat(20)));
+
+    // Locker nonces start at 0, and the locker's nonce does not use up the
+    // committee path's.
+    for (path, expected) in [("EthProof", true), ("Committee", false)] {
+        let processed: bool = bridge.view("is_transfer_processed")
+            .args_json(json!({"source_chain": "eip155:1", "nonce": "0", "path": path}))
+            .await?
+            .json()?;
+        assert_eq!(processed, expected, "{}", path);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_registry_swaps_receipt_tokens() -> anyhow::Result<()> {
+    let worker = near_workspaces::sandbox().await?;
+    let owner = worker.root_account()?;
+
+    let registry_wasm = near_workspaces::compile_project("./contracts/xchain-registry").await?;
+    let registry = worker.dev_deploy(&registry_wasm).await?;
+    registry.call("new")
+        .args_json(json!({"owner_id": owner.id()}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let canonical_asset = CanonicalAssetId {
+        source_chain_id: "eip155:1".parse().unwrap(),
+        source_contract: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string(),
+        asset_standard: AssetStandard::ERC20,
+    };
+    let user = owner.create_subaccount("alice")
+        .initial_balance(NearToken::from_near(10))
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Two competing bridges' receipt tokens for the same asset. `owner` is an
+    // extra route on both so the test can mint without bridge proofs.
+    let token_wasm = near_workspaces::compile_project("./contracts/xchain-token").await?;
+    let mut tokens = Vec::new();
+    let mut bridges = Vec::new();
+    for _ in 0..2 {
+        let bridge = deploy_bridge(&worker, &owner).await?;
+        let token = worker.dev_deploy(&token_wasm).await?;
+        let route = |minter: &near_workspaces::AccountId| json!({
+            "bridge_contract": minter,
+            "security_model": {"Trusted": {"operator": owner.id()}},
+            "deployed_at": 0
+        });
+        token.call("new")
+            .args_json(json!({
+                "owner_id": owner.id(),
+                "metadata": {"spec": "ft-1.0.0", "name": "Bridged USDC", "symbol": "xUSDC", "decimals": 6},
+                "xc_metadata": {
+                    "canonical_asset": canonical_asset,
+                    "bridge_route": route(owner.id()),
+                    "bridge_routes": ([bridge.id(), owner.id()].map(|minter| json!({
+                        "route": route(minter),
+                        "destination_chains": ["eip155:1"],
+                        "supply": "0"
+                    }))),
+                    "total_locked": "0",
+                    "nep141_token": token.id()
+                }
+            }))
+            .transact()
+            .await?
+            .into_result()?;
+        for account in [owner.id(), user.id()] {
+            owner.call(token.id(), "storage_deposit")
+                .args_json(json!({"account_id": account}))
+                .deposit(NearToken::from_millinear(125))
+                .transact()
+                .await?
+                .into_result()?;
+        }
+        owner.call(registry.id(), "register_token")
+            .args_json(json!({"token_id": token.id()}))
+            .deposit(NearToken::from_millinear(125))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+
+        owner.call(bridge.id(), "import_receipt_token")
+            .args_json(json!({"canonical_asset": canonical_asset, "token_id": token.id()}))
+            .transact()
+            .await?
+            .into_result()?;
+        owner.call(registry.id(), "register_bridge")
+            .args_json(json!({"bridge_contract": bridge.id()}))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        owner.call(bridge.id(), "register_receipt_with_registry")
+            .args_json(json!({"registry_id": registry.id(), "token_id": token.id()}))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        let verified: bool = registry.view("is_verified_receipt")
+            .args_json(json!({"token_id": token.id()}))
+            .await?
+            .json()?;
+        assert!(verified, "Receipt token should verify against its bridge");
+        tokens.push(token);
+        bridges.push(bridge);
+    }
+    let (token_a, token_b) = (&tokens[0], &tokens[1]);
+
+    // Only the registry owner registers bridges, and an unregistered bridge
+    // cannot register receipts or take over another bridge's receipt.
+    let rogue = deploy_bridge(&worker, &owner).await?;
+    let result = user.call(registry.id(), "register_bridge")
+        .args_json(json!({"bridge_contract": rogue.id()}))
+        .max_gas()
+        .transact()
+        .await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("Unauthorized"), "{}", failure);
+
+    owner.call(rogue.id(), "import_receipt_token")
+        .args_json(json!({"canonical_asset": canonical_asset, "token_id": token_a.id()}))
+        .transact()
+        .await?
+        .into_result()?;
+    let register_rogue_receipt = || owner.call(rogue.id(), "register_receipt_with_registry")
+        .args_json(json!({"registry_id": registry.id(), "token_id": token_a.id()}))
+        .max_gas()
+        .transact();
+    let failure = format!("{:?}", register_rogue_receipt().await?.into_result().unwrap_err());
+    assert!(failure.contains("Bridge not registered"), "{}", failure);
+
+    owner.call(registry.id(), "register_bridge")
+        .args_json(json!({"bridge_contract": rogue.id()}))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let failure = format!("{:?}", register_rogue_receipt().await?.into_result().unwrap_err());
+    assert!(failure.contains("Receipt registered by another bridge"), "{}", failure);
+    let record: serde_json::Value = registry.view("get_receipt_record")
+        .args_json(json!({"token_id": token_a.id()}))
+        .await?
+        .json()?;
+    assert_eq!(record["bridge_contract"], json!(bridges[0].id()));
+    assert_eq!(record["verified"], json!(true));
+
+    // The deploying bridge can still replace its route with `xc_set_bridge_route`.
+    let new_route = json!({
+        "bridge_contract": owner.id(),
+        "security_model": {"Trusted": {"operator": user.id()}},
+        "deployed_at": 1
+    });
+    owner.call(token_a.id(), "xc_set_bridge_route")
+        .args_json(json!({"bridge_route": new_route}))
+        .transact()
+        .await?
+        .into_result()?;
+    let xc_metadata: serde_json::Value = token_a.view("xc_metadata").await?.json()?;
+    assert_eq!(xc_metadata["bridge_route"], new_route);
+    assert_eq!(xc_metadata["bridge_routes"][1]["route"], new_route);
+
+    // A token that only claims the asset in its own metadata is registered
+    // but not listed.
+    let impostor = worker.dev_deploy(&token_wasm).await?;
+    impostor.call("new")
+        .args_json(json!({
+            "owner_id": owner.id(),
+            "metadata": {"spec": "ft-1.0.0", "name": "Bridged USDC", "symbol": "xUSDC", "decimals": 6},
+            "xc_metadata": {
+                "canonical_asset": canonical_asset,
+                "bridge_route": {
+                    "bridge_contract": owner.id(),
+                    "security_model": {"Trusted": {"operator": owner.id()}},
+                    "deployed_at": 0
+                },
+                "bridge_routes": [],
+                "total_locked": "0",
+                "nep141_token": impostor.id()
+            }
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    owner.call(registry.id(), "register_token")
+        .args_json(json!({"token_id": impostor.id()}))
+        .deposit(NearToken::from_millinear(125))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let asset: Option<CanonicalAssetId> = registry.view("get_token_asset")
+        .args_json(json!({"token_id": impostor.id()}))
+        .await?
+        .json()?;
+    assert!(asset.is_some());
+
+    let registered: Vec<String> = registry.view("get_receipt_tokens")
+        .args_json(json!({"canonical_asset": canonical_asset}))
+        .await?
+        .json()?;
+    assert_eq!(registered, vec![token_a.id().to_string(), token_b.id().to_string()]);
+
+    owner.call(token_a.id(), "mint")
+        .args_json(json!({"account_id": user.id(), "amount": "100"}))
+        .transact()
+        .await?
+        .into_result()?;
+    owner.call(token_b.id(), "mint")
+        .args_json(json!({"account_id": owner.id(), "amount": "1000"}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    owner.call(token_b.id(), "ft_transfer_call")
+        .args_json(json!({"receiver_id": registry.id(), "amount": "1000", "msg": "\"AddLiquidity\""}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    user.call(token_a.id(), "ft_transfer_call")
+        .args_json(json!({
+            "receiver_id": registry.id(),
+            "amount": "100",
+            "msg": json!({"Swap": {"token_out": token_b.id()}}).to_string()
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance: U128 = token_b.view("ft_balance_of")
+        .args_json(json!({"account_id": user.id()}))
+        .await?
+        .json()?;
+    assert_eq!(balance.0, 100);
+    let pool: U128 = registry.view("get_pool")
+        .args_json(json!({"token_id": token_b.id()}))
+        .await?
+        .json()?;
+    assert_eq!(pool.0, 900);
+
+    let liquidity: U128 = registry.view("get_liquidity")
+        .args_json(json!({"account_id": owner.id(), "canonical_asset": canonical_asset}))
+        .await?
+        .json()?;
+    assert_eq!(liquidity.0, 1000);
+    let result = user.call(registry.id(), "remove_liquidity")
+        .args_json(json!({"token_id": token_a.id(), "amount": "100"}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("No liquidity provided"), "{}", failure);
+
+    // token_a loses its verification with its bridge, but providers can
+    // still withdraw it.
+    owner.call(registry.id(), "deregister_bridge")
+        .args_json(json!({"bridge_contract": bridges[0].id()}))
+        .transact()
+        .await?
+        .into_result()?;
+    let verified: bool = registry.view("is_verified_receipt")
+        .args_json(json!({"token_id": token_a.id()}))
+        .await?
+        .json()?;
+    assert!(!verified);
+
+    // The provider's shares are redeemable from both sides of the swap.
+    for (token, amount) in [(token_b, 900), (token_a, 100)] {
+        owner.call(registry.id(), "remove_liquidity")
+            .args_json(json!({"token_id": token.id(), "amount": U128(amount)}))
+            .deposit(NearToken::from_yoctonear(1))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+        let balance: U128 = token.view("ft_balance_of")
+            .args_json(json!({"account_id": owner.id()}))
+            .await?
+            .json()?;
+        assert_eq!(balance.0, amount);
+        let pool: U128 = registry.view("get_pool")
+            .args_json(json!({"token_id": token.id()}))
+            .await?
+            .json()?;
+        assert_eq!(pool.0, 0);
+    }
+    let liquidity: U128 = registry.view("get_liquidity")
+        .args_json(json!({"account_id": owner.id(), "canonical_asset": canonical_asset}))
+        .await?
+        .json()?;
+    assert_eq!(liquidity.0, 0);
+    let result = owner.call(registry.id(), "remove_liquidity")
+        .args_json(json!({"token_id": token_a.id(), "amount": "1"}))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+    let failure = format!("{:?}", result.into_result().unwrap_err());
+    assert!(failure.contains("Amount exceeds provided liquidity"), "{}", failure);
 
-    println!("Messaging test passed!");
     Ok(())
 }